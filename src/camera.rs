@@ -0,0 +1,164 @@
+use rltk::Point;
+use specs::prelude::*;
+
+use crate::{
+    components::{DetectsMonsters, Enemy, Hidden, Position, Renderable},
+    map::{CellType, Map},
+};
+
+// The dimmest a visible cell is drawn, so cells made out in the dark still show
+const MIN_DRAWN_LIGHT: f32 = 0.3;
+
+// The portion of the screen given over to the map; the rest belongs to the HUD
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// The world coordinates of the viewport's top-left and bottom-right (exclusive) corners.
+/// The viewport is centered on the player
+pub fn get_screen_bounds(ecs: &World) -> (i32, i32, i32, i32) {
+    let player_pos = ecs.fetch::<Point>();
+
+    let min_x = player_pos.x - VIEW_WIDTH / 2;
+    let min_y = player_pos.y - VIEW_HEIGHT / 2;
+
+    (min_x, min_x + VIEW_WIDTH, min_y, min_y + VIEW_HEIGHT)
+}
+
+/// Converts a position on the screen into world coordinates
+pub fn screen_to_world(ecs: &World, screen: (i32, i32)) -> Point {
+    let (min_x, _, min_y, _) = get_screen_bounds(ecs);
+
+    Point::new(screen.0 + min_x, screen.1 + min_y)
+}
+
+/// Converts world coordinates into a position on the screen. Yields None if the point is outside the viewport
+pub fn world_to_screen(ecs: &World, world: Point) -> Option<Point> {
+    let (min_x, max_x, min_y, max_y) = get_screen_bounds(ecs);
+
+    if world.x < min_x || world.x >= max_x || world.y < min_y || world.y >= max_y {
+        return None;
+    }
+
+    Some(Point::new(world.x - min_x, world.y - min_y))
+}
+
+fn cell_glyph(cell: CellType) -> (rltk::FontCharType, rltk::RGB) {
+    match cell {
+        CellType::Floor => (rltk::to_cp437('.'), rltk::RGB::from_f32(0., 0.5, 0.5)),
+        CellType::Wall => (rltk::to_cp437('#'), rltk::RGB::from_f32(0., 1., 0.)),
+        CellType::DownStairs => (rltk::to_cp437('>'), rltk::RGB::from_f32(0., 1., 1.)),
+        CellType::ClosedDoor => (rltk::to_cp437('+'), rltk::RGB::named(rltk::CHOCOLATE)),
+        CellType::OpenDoor => (rltk::to_cp437('/'), rltk::RGB::named(rltk::CHOCOLATE)),
+        CellType::ShallowWater => (rltk::to_cp437('~'), rltk::RGB::from_f32(0.3, 0.6, 1.)),
+        CellType::DeepWater => (rltk::to_cp437('≈'), rltk::RGB::from_f32(0., 0.2, 1.)),
+        CellType::Lava => (rltk::to_cp437('≈'), rltk::RGB::named(rltk::ORANGE_RED)),
+        CellType::Grass => (rltk::to_cp437('"'), rltk::RGB::from_f32(0.2, 0.8, 0.2)),
+        CellType::Bridge => (rltk::to_cp437('='), rltk::RGB::named(rltk::CHOCOLATE)),
+        CellType::Rubble => (rltk::to_cp437(','), rltk::RGB::named(rltk::GREY)),
+    }
+}
+
+/// Whether the player is under a detection effect, and so can sense every enemy on the level
+pub fn player_detects_monsters(ecs: &World) -> bool {
+    let player = ecs.fetch::<Entity>();
+
+    ecs.read_storage::<DetectsMonsters>().get(*player).is_some()
+}
+
+/// Tints a color by the light falling on its cell
+fn apply_light(fg: rltk::RGB, light: rltk::RGB) -> rltk::RGB {
+    rltk::RGB::from_f32(
+        fg.r * light.r.max(MIN_DRAWN_LIGHT),
+        fg.g * light.g.max(MIN_DRAWN_LIGHT),
+        fg.b * light.b.max(MIN_DRAWN_LIGHT),
+    )
+}
+
+/// Draws a snapshot of the map taken while it was being built. Every cell is shown whether or not it's been
+/// revealed, and the view is centered on the middle of the map rather than the player
+pub fn render_snapshot(ecs: &World, ctx: &mut rltk::Rltk, cells: &[CellType]) {
+    let map = ecs.fetch::<Map>();
+    let min_x = map.width / 2 - VIEW_WIDTH / 2;
+    let min_y = map.height / 2 - VIEW_HEIGHT / 2;
+
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let x = min_x + screen_x;
+            let y = min_y + screen_y;
+
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let (glyph, fg) = cell_glyph(cells[map.xy_idx(x, y)]);
+            ctx.set(
+                screen_x,
+                screen_y,
+                fg,
+                rltk::RGB::from_f32(0., 0., 0.),
+                glyph,
+            );
+        }
+    }
+}
+
+/// Draws the part of the map and the entities visible through the viewport
+pub fn render_camera(ecs: &World, ctx: &mut rltk::Rltk) {
+    let map = ecs.fetch::<Map>();
+    let (min_x, max_x, min_y, max_y) = get_screen_bounds(ecs);
+
+    for (screen_y, y) in (min_y..max_y).enumerate() {
+        for (screen_x, x) in (min_x..max_x).enumerate() {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if map.revealed_cells[idx] {
+                let (glyph, mut fg) = cell_glyph(map.cells[idx]);
+
+                if map.visible_cells[idx] {
+                    fg = apply_light(fg, map.light[idx]);
+                } else {
+                    fg = fg.to_greyscale()
+                }
+
+                ctx.set(
+                    screen_x,
+                    screen_y,
+                    fg,
+                    rltk::RGB::from_f32(0., 0., 0.),
+                    glyph,
+                );
+            }
+        }
+    }
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let enemies = ecs.read_storage::<Enemy>();
+    let detecting = player_detects_monsters(ecs);
+
+    let mut data = (&positions, &renderables, enemies.maybe(), !&hidden)
+        .join()
+        .collect::<Vec<_>>();
+    data.sort_by_key(|&a| std::cmp::Reverse(a.1.render_order));
+
+    for (pos, render, enemy, _hidden) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+
+        // Enemies sensed but out of sight are drawn faded
+        let fg = if map.visible_cells[idx] {
+            render.fg
+        } else if detecting && enemy.is_some() {
+            render.fg.to_greyscale()
+        } else {
+            continue;
+        };
+
+        if let Some(screen) = world_to_screen(ecs, Point::new(pos.x, pos.y)) {
+            ctx.set(screen.x, screen.y, fg, render.bg, render.glyph);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use rltk::RandomNumberGenerator;
+
+use crate::components::DamageDice;
+
+// The armor class of something with no defense at all
+pub const BASE_ARMOR_CLASS: i32 = 10;
+
+// What anything without a weapon hits with
+pub const UNARMED_DAMAGE: DamageDice = DamageDice {
+    n_dice: 1,
+    die_type: 4,
+};
+
+// How many points of power it takes to add one to damage
+const POWER_PER_DAMAGE: i32 = 2;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AttackOutcome {
+    // A natural 1, which misses whatever the odds
+    Fumble,
+    Miss,
+    Hit,
+    // A natural 20, which hits whatever the odds and rolls its damage dice twice
+    Critical,
+}
+
+/// How hard something with the given defense is to hit
+pub fn armor_class(defense: i32) -> i32 {
+    BASE_ARMOR_CLASS + defense
+}
+
+/// What the attacker's power adds to the damage it deals
+pub fn damage_bonus(power: i32) -> i32 {
+    power / POWER_PER_DAMAGE
+}
+
+/// Settles an attack from its natural d20 roll: the roll plus the attack bonus has to at least meet the armor
+/// class, unless it's a natural 1 or 20
+pub fn attack_outcome(natural_roll: i32, attack_bonus: i32, armor_class: i32) -> AttackOutcome {
+    match natural_roll {
+        1 => AttackOutcome::Fumble,
+        20 => AttackOutcome::Critical,
+        roll if roll + attack_bonus >= armor_class => AttackOutcome::Hit,
+        _ => AttackOutcome::Miss,
+    }
+}
+
+/// Rolls a d20 to attack something with the given armor class
+pub fn roll_attack(
+    rng: &mut RandomNumberGenerator,
+    attack_bonus: i32,
+    armor_class: i32,
+) -> AttackOutcome {
+    attack_outcome(rng.roll_dice(1, 20), attack_bonus, armor_class)
+}
+
+/// Rolls the damage a landed attack deals, doubling the dice on a critical. Anything that lands deals at least 1
+pub fn roll_damage(
+    rng: &mut RandomNumberGenerator,
+    dice: DamageDice,
+    bonus: i32,
+    outcome: AttackOutcome,
+) -> i32 {
+    let n_dice = match outcome {
+        AttackOutcome::Critical => dice.n_dice * 2,
+        _ => dice.n_dice,
+    };
+
+    i32::max(1, rng.roll_dice(n_dice, dice.die_type) + bonus)
+}
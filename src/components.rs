@@ -4,7 +4,9 @@ use specs::prelude::*;
 use specs::saveload::{ConvertSaveload, Marker};
 use specs_derive::*;
 
+use crate::identification::Identification;
 use crate::map::Map;
+use crate::save::SaveMetadata;
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -36,6 +38,26 @@ pub struct Player {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Enemy {}
 
+/// Indicates an entity buys and sells items, its stock being whatever it carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Shopkeeper {}
+
+/// How much gold an entity carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Purse {
+    pub gold: i32,
+}
+
+/// Indicates an entity is a pile of gold, scooped up by anything with a purse that walks over it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct GoldPile {
+    pub amount: i32,
+}
+
+/// Indicates an entity can open closed doors in its way
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct OpensDoors {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String,
@@ -88,7 +110,7 @@ pub struct DesiresAcquireItem {
     pub item: Entity,
 }
 
-#[derive(Component, Debug, ConvertSaveload)]
+#[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct DesiresUseItem {
     pub item: Entity,
     pub target: Option<rltk::Point>,
@@ -99,6 +121,156 @@ pub struct DesiresDropItem {
     pub item: Entity,
 }
 
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct DesiresThrowItem {
+    pub item: Entity,
+    pub target: rltk::Point,
+}
+
+/// Asks to shoot the ammunition from the equipped ranged weapon at the target
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct DesiresFire {
+    pub ammo: Entity,
+    pub target: Entity,
+}
+
+/// Asks to put on the item, or take it off if it's already equipped
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct DesiresEquipItem {
+    pub item: Entity,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DesiresTeleport {
+    pub destination: Option<rltk::Point>,
+}
+
+/// Indicates an entity gives off light, lighting cells in view out to its radius
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LightSource {
+    pub color: rltk::RGB,
+    pub radius: i32,
+    pub lit: bool,
+}
+
+/// How heavy an item is to carry
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+    pub weight: i32,
+}
+
+impl Weight {
+    /// The weight of the whole stack, or of the item alone if it isn't stacked
+    pub fn of_stack(&self, stack: Option<&Stackable>) -> i32 {
+        self.weight * stack.map_or(1, |stack| stack.quantity)
+    }
+}
+
+/// What an item is worth to a shopkeeper, in gold. Items without one can't be bought or sold
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Value {
+    pub gold: i32,
+}
+
+// How much weight each point of strength lets an entity carry
+const CAPACITY_PER_STRENGTH: i32 = 3;
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Attributes {
+    pub strength: i32,
+    pub dexterity: i32,
+}
+
+impl Attributes {
+    /// The most weight the entity can carry
+    pub fn carrying_capacity(&self) -> i32 {
+        self.strength * CAPACITY_PER_STRENGTH
+    }
+
+    /// What the entity's dexterity adds to, or takes from, its aim with ranged weapons
+    pub fn dexterity_bonus(&self) -> i32 {
+        (self.dexterity - 10) / 2
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+    Ranged,
+}
+
+/// Indicates an item can be worn or wielded in the given slot
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+/// The dice a weapon rolls for damage when it lands a blow
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct DamageDice {
+    pub n_dice: i32,
+    pub die_type: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AmmoType {
+    Arrow,
+    Bolt,
+}
+
+/// Indicates an item shoots ammunition of the given type at anything within range
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub damage: DamageDice,
+    pub ammo: AmmoType,
+}
+
+/// Indicates an item can be shot from a ranged weapon taking its type
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ammunition {
+    pub ammo: AmmoType,
+}
+
+/// Indicates an item can't be taken off or dropped once equipped. The player doesn't know until they put it on
+/// or identify it
+#[derive(Component, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Cursed {
+    pub known: bool,
+}
+
+/// Indicates an item lifts the curses on everything its user carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RemovesCurse {}
+
+/// Indicates an item merges with others of the same name in an inventory, standing in for quantity of them.
+/// Everything stackable is spawned identically, so a name is enough to tell two apart
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Stackable {
+    pub quantity: i32,
+}
+
+/// Indicates an item breaks when thrown, splashing its effect over the cells around where it lands
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Shatters {}
+
 /// Indicates an item can be used (at which point it is destroyed)
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumable {}
@@ -123,9 +295,41 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
+/// How many more times an item can be used. Empty items crumble away if destroyed_when_empty, and otherwise
+/// sit inert until recharged
+#[derive(Component, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Charges {
+    pub current: i32,
+    pub max: i32,
+    pub destroyed_when_empty: bool,
+}
+
+/// Indicates an item refills the charges of everything its user carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Recharges {}
+
+/// Indicates an item identifies everything its user carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Identifies {}
+
+/// Indicates an item teleports its user; to the targeted cell if it's ranged, anywhere on the level if not
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Teleports {}
+
+/// Indicates an enemy can blink to the player's side from up to range cells away
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeleportSpell {
+    pub range: i32,
+}
+
+/// Indicates an item reveals the whole level when used
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicMapper {}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum StatusEffectType {
     Confusion,
+    Sleep,
 }
 
 #[derive(Component, Debug, Clone, ConvertSaveload)]
@@ -135,9 +339,46 @@ pub struct StatusEffect {
     pub turns: i32,
 }
 
+/// On an item, lets whoever uses it sense every enemy on the level for the given number of turns. On anything
+/// else, how many of those turns it has left. Kept apart from StatusEffect, so it never displaces confusion or
+/// sleep
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DetectsMonsters {
+    pub turns: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrapType {
+    Spike,
+    PoisonDart,
+    Teleport,
+    Alarm,
+    Trapdoor,
+}
+
+/// Indicates an entity fires when something enters its cell
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Trap {
+    pub kind: TrapType,
+}
+
+/// Indicates an entity isn't rendered until it has been detected
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+/// Indicates an entity changed cells this turn
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntityMoved {}
+
+/// Indicates an enemy knows where the player is, whether or not it can see them
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Alerted {}
+
 pub struct SerializeOnSave;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: Map,
+    pub metadata: SaveMetadata,
+    pub identification: Identification,
 }
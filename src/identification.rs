@@ -0,0 +1,96 @@
+use std::collections::{HashMap, HashSet};
+
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Name;
+
+// The potions and scrolls that go unrecognised until identified
+const POTIONS: &[&str] = &["Health Potion", "Detect Monsters Potion"];
+const SCROLLS: &[&str] = &[
+    "Magic Missile Scroll",
+    "Fireball Scroll",
+    "Confusion Scroll",
+    "Magic Mapping Scroll",
+    "Teleport Scroll",
+    "Blink Scroll",
+    "Recharge Scroll",
+    "Identify Scroll",
+    "Remove Curse Scroll",
+];
+
+// What unidentified potions look like
+const POTION_TEXTURES: &[&str] = &[
+    "bubbling", "murky", "fizzing", "smoking", "glowing", "cloudy", "oily", "swirling",
+];
+const POTION_COLORS: &[&str] = &[
+    "violet", "crimson", "amber", "teal", "silver", "green", "black", "pink",
+];
+
+// The sounds unidentified scroll labels are made up of
+const SCROLL_SYLLABLES: &[&str] = &[
+    "ka", "zu", "mor", "el", "xy", "zzy", "fen", "tho", "rak", "ulu", "bi", "qua", "nox", "ish",
+];
+
+/// The disguises potions and scrolls wear this run, and which of them the player has seen through
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Identification {
+    appearances: HashMap<String, String>,
+    identified: HashSet<String>,
+}
+
+impl Identification {
+    /// Gives every potion and scroll a different random appearance
+    pub fn new(rng: &mut RandomNumberGenerator) -> Identification {
+        let mut appearances: HashMap<String, String> = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+
+        for potion in POTIONS {
+            let appearance = loop {
+                let texture = rng.random_slice_entry(POTION_TEXTURES).unwrap();
+                let color = rng.random_slice_entry(POTION_COLORS).unwrap();
+                let appearance = format!("{} {} potion", texture, color);
+
+                if used.insert(appearance.clone()) {
+                    break appearance;
+                }
+            };
+
+            appearances.insert(potion.to_string(), appearance);
+        }
+
+        for scroll in SCROLLS {
+            let appearance = loop {
+                let label: String = (0..rng.range(2, 4))
+                    .map(|_| *rng.random_slice_entry(SCROLL_SYLLABLES).unwrap())
+                    .collect();
+                let appearance = format!("scroll labeled {}", label.to_uppercase());
+
+                if used.insert(appearance.clone()) {
+                    break appearance;
+                }
+            };
+
+            appearances.insert(scroll.to_string(), appearance);
+        }
+
+        Identification {
+            appearances,
+            identified: HashSet::new(),
+        }
+    }
+
+    /// What the player knows the named thing as: its appearance if it's a potion or scroll they haven't
+    /// identified, or its true name otherwise
+    pub fn name_of(&self, name: &Name) -> String {
+        match self.appearances.get(&name.name) {
+            Some(appearance) if !self.identified.contains(&name.name) => appearance.to_string(),
+            _ => name.name.to_string(),
+        }
+    }
+
+    /// Reveals the true name of everything sharing the given name. Returns false if there was nothing to learn
+    pub fn identify(&mut self, name: &Name) -> bool {
+        self.appearances.contains_key(&name.name) && self.identified.insert(name.name.to_string())
+    }
+}
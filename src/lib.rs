@@ -1,9 +1,15 @@
+pub mod camera;
+pub mod combat;
 pub mod components;
+pub mod identification;
 pub mod log;
 pub mod map;
+pub mod map_builders;
 pub mod player;
 pub mod random_table;
+pub mod registry;
 pub mod save;
+pub mod shop;
 pub mod spawn;
 pub mod state;
 
@@ -1,18 +1,13 @@
 use rltk::{self, Point};
 use specs::prelude::*;
-use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
-use whatever::components::SerializationHelper;
+use specs::saveload::SimpleMarkerAllocator;
 use whatever::{
-    components::{
-        AreaOfEffect, BlocksCell, CombatStats, Consumable, Damage, DesiresAcquireItem,
-        DesiresDropItem, DesiresMelee, DesiresUseItem, Enemy, InInventory, InflictsDamage, Item,
-        Name, Player, Position, ProvidesHealing, Ranged, Renderable, SerializeOnSave, StatusEffect,
-        Viewshed,
-    },
+    components::SerializeOnSave,
+    identification::Identification,
     log,
     map::Map,
-    spawn,
-    state::{RunState, State},
+    registry, save, spawn,
+    state::{MapGenerationReplay, RunState, State},
     ui,
 };
 
@@ -26,48 +21,35 @@ fn main() -> rltk::BError {
 
     let mut gs = State { ecs: World::new() };
 
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Enemy>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksCell>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<DesiresMelee>();
-    gs.ecs.register::<Damage>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<InInventory>();
-    gs.ecs.register::<DesiresAcquireItem>();
-    gs.ecs.register::<DesiresUseItem>();
-    gs.ecs.register::<DesiresDropItem>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<StatusEffect>();
-    gs.ecs.register::<SimpleMarker<SerializeOnSave>>();
-    gs.ecs.register::<SerializationHelper>();
-
-    let map = Map::generate_map_rooms_and_tunnels(1);
-
-    let (x, y) = map.rooms[0].center();
+    registry::register_components(&mut gs.ecs);
 
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
     gs.ecs
         .insert(SimpleMarkerAllocator::<SerializeOnSave>::new());
+    gs.ecs.insert(save::AutosaveTimer::default());
+
+    let metadata = save::SaveMetadata::new_run(
+        &mut gs.ecs.fetch_mut::<rltk::RandomNumberGenerator>(),
+        false,
+    );
+    gs.ecs.insert(metadata);
+
+    let identification =
+        Identification::new(&mut gs.ecs.fetch_mut::<rltk::RandomNumberGenerator>());
+    gs.ecs.insert(identification);
+
+    gs.ecs.insert(Map::default());
+    gs.ecs.insert(MapGenerationReplay {
+        enabled: std::env::args().any(|arg| arg == "--show-mapgen"),
+        ..Default::default()
+    });
 
-    // Skip room player spawned in
-    for room in map.rooms.iter().skip(1) {
-        spawn::room(&mut gs.ecs, room, 1);
-    }
-
-    let player_entity = spawn::player(&mut gs.ecs, x, y);
+    // The player is moved to the map's starting position once it's generated
+    let player_entity = spawn::player(&mut gs.ecs, 0, 0);
 
     gs.ecs.insert(player_entity);
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(x, y));
+    gs.ecs.insert(Point::new(0, 0));
+
     gs.ecs.insert(RunState::MainMenu {
         menu_selection: ui::MainMenuSelection::NewGame,
     });
@@ -75,5 +57,7 @@ fn main() -> rltk::BError {
         entries: vec!["lil boo mane - lil booney".to_string()],
     });
 
+    gs.generate_world_map(1);
+
     rltk::main_loop(ctx, gs)
 }
@@ -1,27 +1,46 @@
-use crate::components::Player;
-use crate::{components::Viewshed, geometry::Rect};
-use rltk::{self, Algorithm2D, BaseMap, Point, RandomNumberGenerator};
+use crate::geometry::Rect;
+use rltk::{self, Algorithm2D, BaseMap, Point};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use specs::World;
 use std::cmp::{max, min};
 
-pub const MAP_WIDTH: i32 = 80;
-pub const MAP_HEIGHT: i32 = 43;
-pub const MAP_COUNT: usize = (MAP_HEIGHT * MAP_WIDTH) as usize;
+// How bright a cell must be for anything in it to be seen from afar
+const MIN_VISIBLE_LIGHT: f32 = 0.1;
 
-// The max number of rooms to generate
-const MAX_ROOMS: i32 = 30;
+// Added to the cost of pathing through a known trap, so entities walk around them where they can
+const TRAP_AVOIDANCE_COST: f32 = 10.0;
 
-// Rand ranges
-const MIN_SIZE: i32 = 6;
-const MAX_SIZE: i32 = 10;
-
-#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum CellType {
     Wall,
     Floor,
     DownStairs,
+    ClosedDoor,
+    OpenDoor,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Grass,
+    Bridge,
+    Rubble,
+}
+
+impl CellType {
+    /// Whether anything could ever stand in the cell. Closed doors count, as they can be opened
+    pub fn is_walkable(self) -> bool {
+        self != CellType::Wall
+    }
+
+    /// What stepping into the cell costs pathing, compared to bare floor
+    pub fn movement_cost(self) -> f32 {
+        match self {
+            CellType::ShallowWater | CellType::Rubble => 2.0,
+            CellType::DeepWater => 4.0,
+            // Nothing walks through lava if there's any other way round
+            CellType::Lava => 20.0,
+            _ => 1.0,
+        }
+    }
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -36,13 +55,51 @@ pub struct Map {
     pub blocked: Vec<bool>,
     // The depth of the map i.e. floors
     pub depth: i32,
+    // Does the point hold a trap the player knows about?
+    pub revealed_traps: Vec<bool>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub cell_content: Vec<Vec<Entity>>,
+
+    // The light falling on each cell, rebuilt from the light sources every turn
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub light: Vec<rltk::RGB>,
 }
 
 impl Map {
+    /// Creates a map of solid wall with the given dimensions
+    pub fn new(depth: i32, width: i32, height: i32) -> Map {
+        let count = (width * height) as usize;
+
+        Map {
+            cells: vec![CellType::Wall; count],
+            revealed_cells: vec![false; count],
+            visible_cells: vec![false; count],
+            blocked: vec![false; count],
+            rooms: Vec::new(),
+            width,
+            height,
+            cell_content: vec![Vec::new(); count],
+            depth,
+            revealed_traps: vec![false; count],
+            light: vec![rltk::RGB::from_f32(0., 0., 0.); count],
+        }
+    }
+
+    /// How brightly lit the cell is, from 0 for pitch dark to 1 for full light
+    pub fn light_level(&self, idx: usize) -> f32 {
+        let light = self.light[idx];
+
+        light.r.max(light.g).max(light.b)
+    }
+
+    /// Whether the cell is bright enough to make out from a distance
+    pub fn is_lit(&self, idx: usize) -> bool {
+        self.light_level(idx) >= MIN_VISIBLE_LIGHT
+    }
+
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
         (y as usize * self.width as usize) + x as usize
     }
@@ -66,14 +123,56 @@ impl Map {
         !self.blocked[idx]
     }
 
-    /// Sets blocked on a cell if its a wall
+    /// The cost of stepping into idx, given the base cost of the step
+    fn exit_cost(&self, idx: usize, base: f32) -> f32 {
+        let cost = base * self.cells[idx].movement_cost();
+
+        if self.revealed_traps[idx] {
+            cost + TRAP_AVOIDANCE_COST
+        } else {
+            cost
+        }
+    }
+
+    /// Sets blocked on a cell if its a wall or a closed door
     pub fn populate_blocked(&mut self) {
         for (i, cell) in self.cells.iter_mut().enumerate() {
-            self.blocked[i] = *cell == CellType::Wall;
+            self.blocked[i] = !cell.is_walkable() || *cell == CellType::ClosedDoor;
         }
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
+    /// Sets blocked on every closed door, so entities that can open doors may path through them
+    pub fn set_closed_doors_blocked(&mut self, blocked: bool) {
+        for (i, cell) in self.cells.iter().enumerate() {
+            if *cell == CellType::ClosedDoor {
+                self.blocked[i] = blocked;
+            }
+        }
+    }
+
+    /// Opens the door at idx; returns false if there's no closed door there
+    pub fn open_door(&mut self, idx: usize) -> bool {
+        if self.cells[idx] != CellType::ClosedDoor {
+            return false;
+        }
+
+        self.cells[idx] = CellType::OpenDoor;
+        self.blocked[idx] = false;
+        true
+    }
+
+    /// Closes the door at idx; returns false if there's no open door there, or something is standing in it
+    pub fn close_door(&mut self, idx: usize) -> bool {
+        if self.cells[idx] != CellType::OpenDoor || !self.cell_content[idx].is_empty() {
+            return false;
+        }
+
+        self.cells[idx] = CellType::ClosedDoor;
+        self.blocked[idx] = true;
+        true
+    }
+
+    pub(crate) fn apply_room_to_map(&mut self, room: &Rect) {
         for y in room.y1 + 1..=room.y2 {
             for x in room.x1 + 1..=room.x2 {
                 let idx = self.xy_idx(x, y);
@@ -82,7 +181,7 @@ impl Map {
         }
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
+    pub(crate) fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
         for x in min(x1, x2)..=max(x1, x2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
@@ -91,7 +190,7 @@ impl Map {
         }
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
+    pub(crate) fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
         for y in min(y1, y2)..=max(y1, y2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
@@ -100,65 +199,36 @@ impl Map {
         }
     }
 
-    /// Generates a map of rooms and connecting tunnels
-    pub fn generate_map_rooms_and_tunnels(depth: i32) -> Map {
-        let mut map = Map {
-            cells: vec![CellType::Wall; MAP_COUNT],
-            revealed_cells: vec![false; MAP_COUNT],
-            visible_cells: vec![false; MAP_COUNT],
-            blocked: vec![false; MAP_COUNT],
-            rooms: Vec::new(),
-            width: MAP_WIDTH,
-            height: MAP_HEIGHT,
-            cell_content: vec![Vec::new(); MAP_COUNT],
-            depth,
-        };
+    /// Returns a bool indicating whether the cell is a floor in a one-cell-wide gap between walls
+    fn is_door_candidate(&self, x: i32, y: i32) -> bool {
+        if x < 1 || x > self.width - 2 || y < 1 || y > self.height - 2 {
+            return false;
+        }
+
+        if self.cells[self.xy_idx(x, y)] != CellType::Floor {
+            return false;
+        }
 
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..MAX_ROOMS {
-            // Generate random width and height
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-
-            // Generate the room center
-            let x = rng.roll_dice(1, MAP_WIDTH - w - 1) - 1;
-            let y = rng.roll_dice(1, MAP_HEIGHT - h - 1) - 1;
-
-            // Given w/h and x/y, generate the room
-            let new_room = Rect::new(x, y, w, h);
-
-            // If the room doesn't overlap with any of the others we've generated...
-            if !map
-                .rooms
-                .iter()
-                .any(|other_room| new_room.intersects(other_room))
-            {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-
-                    // Place tunnels between this room and the previous room
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
+        let is_wall = |x: i32, y: i32| self.cells[self.xy_idx(x, y)] == CellType::Wall;
+
+        (is_wall(x - 1, y) && is_wall(x + 1, y)) || (is_wall(x, y - 1) && is_wall(x, y + 1))
+    }
+
+    /// Places closed doors wherever a tunnel passes through the wall around a room
+    pub(crate) fn apply_doors(&mut self) {
+        for room in self.rooms.clone().iter() {
+            for y in room.y1..=room.y2 + 1 {
+                for x in room.x1..=room.x2 + 1 {
+                    let on_wall =
+                        x == room.x1 || x == room.x2 + 1 || y == room.y1 || y == room.y2 + 1;
+
+                    if on_wall && self.is_door_candidate(x, y) {
+                        let idx = self.xy_idx(x, y);
+                        self.cells[idx] = CellType::ClosedDoor;
                     }
                 }
-
-                map.rooms.push(new_room);
             }
         }
-
-        let stairs_pos = map.rooms[map.rooms.len() - 1].center();
-        let stairs_idx = map.xy_idx(stairs_pos.0, stairs_pos.1);
-        map.cells[stairs_idx] = CellType::DownStairs;
-
-        map
     }
 }
 
@@ -170,7 +240,7 @@ impl Algorithm2D for Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.cells[idx] == CellType::Wall
+        self.cells[idx] == CellType::Wall || self.cells[idx] == CellType::ClosedDoor
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::prelude::SmallVec<[(usize, f32); 10]> {
@@ -181,36 +251,36 @@ impl BaseMap for Map {
 
         // Cardinal directions
         if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
+            exits.push((idx - 1, self.exit_cost(idx - 1, 1.0)))
         };
 
         if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
+            exits.push((idx + 1, self.exit_cost(idx + 1, 1.0)))
         };
 
         if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
+            exits.push((idx - w, self.exit_cost(idx - w, 1.0)))
         };
 
         if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
+            exits.push((idx + w, self.exit_cost(idx + w, 1.0)))
         };
 
         // Diagonals
         if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45));
+            exits.push(((idx - w) - 1, self.exit_cost((idx - w) - 1, 1.45)));
         }
 
         if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45));
+            exits.push(((idx - w) + 1, self.exit_cost((idx - w) + 1, 1.45)));
         }
 
         if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45));
+            exits.push(((idx + w) - 1, self.exit_cost((idx + w) - 1, 1.45)));
         }
 
         if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45));
+            exits.push(((idx + w) + 1, self.exit_cost((idx + w) + 1, 1.45)));
         }
 
         exits
@@ -224,37 +294,3 @@ impl BaseMap for Map {
         rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
     }
 }
-
-pub fn draw_map(ecs: &World, ctx: &mut rltk::Rltk) {
-    let map = ecs.fetch::<Map>();
-    let mut viewsheds = ecs.write_storage::<Viewshed>();
-    let mut players = ecs.write_storage::<Player>();
-
-    for (_player, _viewshed) in (&mut players, &mut viewsheds).join() {
-        let mut x = 0;
-        let mut y = 0;
-
-        for (idx, cell) in map.cells.iter().enumerate() {
-            if map.revealed_cells[idx] {
-                let (glyph, mut fg) = match cell {
-                    CellType::Floor => (rltk::to_cp437('.'), rltk::RGB::from_f32(0., 0.5, 0.5)),
-                    CellType::Wall => (rltk::to_cp437('#'), rltk::RGB::from_f32(0., 1., 0.)),
-                    CellType::DownStairs => (rltk::to_cp437('>'), rltk::RGB::from_f32(0., 1., 1.)),
-                };
-
-                if !map.visible_cells[idx] {
-                    fg = fg.to_greyscale()
-                }
-
-                ctx.set(x, y, fg, rltk::RGB::from_f32(0., 0., 0.), glyph);
-            }
-
-            // Move coords to next
-            x += 1;
-            if x > MAP_WIDTH - 1 {
-                x = 0;
-                y += 1;
-            }
-        }
-    }
-}
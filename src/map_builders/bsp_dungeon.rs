@@ -0,0 +1,228 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::Position,
+    geometry::Rect,
+    map::{CellType, Map},
+    spawn,
+};
+
+const BSP_MAP_WIDTH: i32 = 80;
+const BSP_MAP_HEIGHT: i32 = 50;
+
+// Leaves are split until neither side is larger than this
+const MAX_LEAF_SIZE: i32 = 16;
+
+// Leaves are never split smaller than this
+const MIN_LEAF_SIZE: i32 = 7;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CorridorStyle {
+    // A direct line between consecutive rooms
+    Straight,
+    // An L-shaped tunnel between consecutive rooms
+    Dogleg,
+    // Each room is joined to the nearest room already connected
+    NearestNeighbour,
+}
+
+/// Tightly packed rooms made by recursively partitioning the map
+pub struct BspDungeonBuilder {
+    map: Map,
+    depth: i32,
+    corridors: CorridorStyle,
+    history: Vec<Vec<CellType>>,
+}
+
+impl BspDungeonBuilder {
+    pub fn new(depth: i32, corridors: CorridorStyle) -> BspDungeonBuilder {
+        BspDungeonBuilder {
+            map: Map::default(),
+            depth,
+            corridors,
+            history: Vec::new(),
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+
+    /// Splits the area along its longer side until every leaf is small enough to hold a single room
+    fn partition(&self, area: Rect, rng: &mut RandomNumberGenerator, leaves: &mut Vec<Rect>) {
+        let width = area.x2 - area.x1;
+        let height = area.y2 - area.y1;
+
+        if width <= MAX_LEAF_SIZE && height <= MAX_LEAF_SIZE {
+            leaves.push(area);
+            return;
+        }
+
+        if width >= height {
+            let split = area.x1 + rng.range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+
+            self.partition(
+                Rect::new(area.x1, area.y1, split - area.x1, height),
+                rng,
+                leaves,
+            );
+            self.partition(
+                Rect::new(split, area.y1, area.x2 - split, height),
+                rng,
+                leaves,
+            );
+        } else {
+            let split = area.y1 + rng.range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+
+            self.partition(
+                Rect::new(area.x1, area.y1, width, split - area.y1),
+                rng,
+                leaves,
+            );
+            self.partition(
+                Rect::new(area.x1, split, width, area.y2 - split),
+                rng,
+                leaves,
+            );
+        }
+    }
+
+    /// Fits a room inside the leaf, leaving a wall between it and its neighbours
+    fn room_in_leaf(leaf: &Rect, rng: &mut RandomNumberGenerator) -> Rect {
+        let width = leaf.x2 - leaf.x1 - 1;
+        let height = leaf.y2 - leaf.y1 - 1;
+
+        // Shave a little off some rooms so they don't all line up
+        let shrink_x = rng.range(0, 2);
+        let shrink_y = rng.range(0, 2);
+
+        Rect::new(leaf.x1, leaf.y1, width - shrink_x, height - shrink_y)
+    }
+
+    fn apply_straight_corridor(&mut self, from: (i32, i32), to: (i32, i32)) {
+        let line = rltk::line2d(
+            rltk::LineAlg::Bresenham,
+            Point::new(from.0, from.1),
+            Point::new(to.0, to.1),
+        );
+
+        for cell in line.iter() {
+            let idx = self.map.xy_idx(cell.x, cell.y);
+            self.map.cells[idx] = CellType::Floor;
+        }
+    }
+
+    fn apply_dogleg_corridor(
+        &mut self,
+        from: (i32, i32),
+        to: (i32, i32),
+        rng: &mut RandomNumberGenerator,
+    ) {
+        if rng.range(0, 2) == 1 {
+            self.map.apply_horizontal_tunnel(from.0, to.0, from.1);
+            self.map.apply_vertical_tunnel(from.1, to.1, to.0);
+        } else {
+            self.map.apply_vertical_tunnel(from.1, to.1, from.0);
+            self.map.apply_horizontal_tunnel(from.0, to.0, to.1);
+        }
+    }
+
+    fn apply_corridors(&mut self, rng: &mut RandomNumberGenerator) {
+        let rooms = self.map.rooms.clone();
+
+        match self.corridors {
+            CorridorStyle::Straight => {
+                for pair in rooms.windows(2) {
+                    self.apply_straight_corridor(pair[0].center(), pair[1].center());
+                    self.take_snapshot();
+                }
+            }
+
+            CorridorStyle::Dogleg => {
+                for pair in rooms.windows(2) {
+                    self.apply_dogleg_corridor(pair[0].center(), pair[1].center(), rng);
+                    self.take_snapshot();
+                }
+            }
+
+            CorridorStyle::NearestNeighbour => {
+                let mut connected: Vec<usize> = vec![0];
+
+                for i in 1..rooms.len() {
+                    let (x, y) = rooms[i].center();
+
+                    let nearest = connected
+                        .iter()
+                        .map(|j| {
+                            let (other_x, other_y) = rooms[*j].center();
+                            let distance = rltk::DistanceAlg::Pythagoras
+                                .distance2d(Point::new(x, y), Point::new(other_x, other_y));
+
+                            (*j, distance)
+                        })
+                        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                        .map(|(j, _)| j)
+                        .unwrap();
+
+                    self.apply_dogleg_corridor(rooms[nearest].center(), (x, y), rng);
+                    connected.push(i);
+                    self.take_snapshot();
+                }
+            }
+        }
+    }
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map = Map::new(self.depth, BSP_MAP_WIDTH, BSP_MAP_HEIGHT);
+
+        let mut leaves: Vec<Rect> = Vec::new();
+        self.partition(
+            Rect::new(1, 1, self.map.width - 2, self.map.height - 2),
+            rng,
+            &mut leaves,
+        );
+
+        for leaf in leaves.iter() {
+            let room = BspDungeonBuilder::room_in_leaf(leaf, rng);
+
+            self.map.apply_room_to_map(&room);
+            self.map.rooms.push(room);
+            self.take_snapshot();
+        }
+
+        self.apply_corridors(rng);
+        self.map.apply_doors();
+
+        // Stairs go as far from the start as can be walked
+        let start = self.get_starting_position();
+        let start_idx = self.map.xy_idx(start.x, start.y);
+        let stairs_idx = common::cull_unreachable(&mut self.map, start_idx);
+        self.map.cells[stairs_idx] = CellType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        // Skip room player spawned in
+        for room in self.map.rooms.iter().skip(1) {
+            spawn::room(ecs, room, self.depth);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        let (x, y) = self.map.rooms[0].center();
+
+        Position { x, y }
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::Position,
+    map::{CellType, Map},
+    spawn,
+};
+
+const CAVE_MAP_WIDTH: i32 = 90;
+const CAVE_MAP_HEIGHT: i32 = 60;
+
+// The chance, out of 100, that a cell starts out as a wall
+const WALL_CHANCE: i32 = 55;
+
+// How many times the noise is smoothed into caves
+const SMOOTHING_ITERATIONS: i32 = 15;
+
+// The width and height of the regions spawns are spread over
+const REGION_SIZE: i32 = 16;
+
+/// Organic caves grown from random noise
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    regions: HashMap<i32, Vec<usize>>,
+    history: Vec<Vec<CellType>>,
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(depth: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth,
+            regions: HashMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+
+    /// Fills everything but the outer edge with random walls and floors
+    fn apply_noise(&mut self, rng: &mut RandomNumberGenerator) {
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+
+                self.map.cells[idx] = if rng.roll_dice(1, 100) <= WALL_CHANCE {
+                    CellType::Wall
+                } else {
+                    CellType::Floor
+                };
+            }
+        }
+    }
+
+    /// Turns cells with many neighbouring walls, or none at all, into walls and everything else into floor
+    fn smooth(&mut self) {
+        let mut new_cells = self.map.cells.clone();
+
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let mut neighbours = 0;
+
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let is_self = dx == 0 && dy == 0;
+                        if !is_self
+                            && self.map.cells[self.map.xy_idx(x + dx, y + dy)] == CellType::Wall
+                        {
+                            neighbours += 1;
+                        }
+                    }
+                }
+
+                let idx = self.map.xy_idx(x, y);
+                new_cells[idx] = if neighbours > 4 || neighbours == 0 {
+                    CellType::Wall
+                } else {
+                    CellType::Floor
+                };
+            }
+        }
+
+        self.map.cells = new_cells;
+    }
+
+    /// Starts on the floor nearest the middle of the map. A map with no floor at all keeps the middle, and fails
+    /// validation
+    fn find_starting_position(&mut self) -> usize {
+        let middle = Point::new(self.map.width / 2, self.map.height / 2);
+        let idx = common::nearest_floor(&self.map, middle)
+            .unwrap_or_else(|| self.map.xy_idx(middle.x, middle.y));
+
+        self.starting_position = Position {
+            x: idx as i32 % self.map.width,
+            y: idx as i32 / self.map.width,
+        };
+
+        idx
+    }
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map = Map::new(self.depth, CAVE_MAP_WIDTH, CAVE_MAP_HEIGHT);
+
+        self.apply_noise(rng);
+        self.take_snapshot();
+
+        for _ in 0..SMOOTHING_ITERATIONS {
+            self.smooth();
+            self.take_snapshot();
+        }
+
+        let start_idx = self.find_starting_position();
+
+        // Stairs go as far from the start as the caves allow
+        let stairs_idx = common::cull_unreachable(&mut self.map, start_idx);
+        self.map.cells[stairs_idx] = CellType::DownStairs;
+        self.take_snapshot();
+
+        self.regions = common::floor_regions(&self.map, REGION_SIZE);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        // Skip region player spawned in
+        for area in self.regions.values() {
+            if !area.contains(&start_idx) {
+                spawn::region(ecs, area, self.depth);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
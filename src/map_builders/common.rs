@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use rltk::Point;
+
+use crate::map::{CellType, Map};
+
+// The furthest a Dijkstra map will search from its start, in steps
+pub const DIJKSTRA_MAX_DEPTH: f32 = 2000.0;
+
+/// Walls off every walkable cell that can't be reached from start_idx.
+/// Returns the reachable floor cell farthest from start_idx
+pub fn cull_unreachable(map: &mut Map, start_idx: usize) -> usize {
+    map.populate_blocked();
+
+    // Doors can be opened, so they don't cut anything off
+    map.set_closed_doors_blocked(false);
+
+    let dijkstra = rltk::DijkstraMap::new(
+        map.width,
+        map.height,
+        &[start_idx],
+        &*map,
+        DIJKSTRA_MAX_DEPTH,
+    );
+
+    let mut farthest_idx = start_idx;
+    let mut farthest_distance = 0.0;
+
+    for (idx, cell) in map.cells.iter_mut().enumerate() {
+        if !cell.is_walkable() || *cell == CellType::DownStairs {
+            continue;
+        }
+
+        let distance = dijkstra.map[idx];
+
+        if distance == f32::MAX {
+            *cell = CellType::Wall;
+        } else if distance > farthest_distance && *cell == CellType::Floor {
+            farthest_idx = idx;
+            farthest_distance = distance;
+        }
+    }
+
+    farthest_idx
+}
+
+/// Groups the map's floor cells into square regions of the given size, for spreading spawns across maps that
+/// have no rooms
+pub fn floor_regions(map: &Map, region_size: i32) -> HashMap<i32, Vec<usize>> {
+    let regions_per_row = (map.width + region_size - 1) / region_size;
+    let mut regions: HashMap<i32, Vec<usize>> = HashMap::new();
+
+    for (idx, cell) in map.cells.iter().enumerate() {
+        if *cell != CellType::Floor {
+            continue;
+        }
+
+        let x = idx as i32 % map.width;
+        let y = idx as i32 / map.width;
+        let region = (y / region_size) * regions_per_row + (x / region_size);
+
+        regions.entry(region).or_default().push(idx);
+    }
+
+    regions
+}
+
+/// The floor cell nearest the given point, if the map has any floor at all
+pub fn nearest_floor(map: &Map, point: Point) -> Option<usize> {
+    map.cells
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| **cell == CellType::Floor)
+        .map(|(idx, _)| {
+            let cell = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+            (idx, rltk::DistanceAlg::Pythagoras.distance2d(point, cell))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(idx, _)| idx)
+}
@@ -0,0 +1,117 @@
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+use crate::{
+    components::Position,
+    map::{CellType, Map},
+};
+
+mod bsp_dungeon;
+mod cellular_automata;
+mod common;
+mod prefab;
+mod rooms_and_tunnels;
+mod terrain;
+mod validation;
+mod waveform_collapse;
+
+pub use bsp_dungeon::{BspDungeonBuilder, CorridorStyle};
+pub use cellular_automata::CellularAutomataBuilder;
+pub use prefab::{
+    HorizontalPlacement, PrefabBuilder, PrefabLevel, PrefabRoom, PrefabSection, VerticalPlacement,
+    LEVELS, SECTIONS, VAULTS,
+};
+pub use rooms_and_tunnels::RoomsAndTunnelsBuilder;
+pub use terrain::TerrainBuilder;
+pub use validation::{validate_level, LevelProblem};
+pub use waveform_collapse::WaveformCollapseBuilder;
+
+// How many levels in a row may fail validation before falling back to a hand-drawn one
+const MAX_GENERATION_ATTEMPTS: i32 = 100;
+
+/// A level generator
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn spawn_entities(&mut self, ecs: &mut World);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    /// The map's cells at each step of building it, for watching the generator work
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>>;
+}
+
+/// Picks one of the available generators for the given depth
+pub fn random_builder(depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    // Every so often the whole level is hand-drawn
+    if rng.roll_dice(1, 20) == 1 {
+        let level = rng.random_slice_entry(LEVELS).expect("no prefab levels");
+
+        return Box::new(PrefabBuilder::level(depth, level));
+    }
+
+    let mut builder: Box<dyn MapBuilder> = match rng.roll_dice(1, 7) {
+        1 => Box::new(RoomsAndTunnelsBuilder::new(depth)),
+        2 => Box::new(CellularAutomataBuilder::new(depth)),
+        3 => Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Straight)),
+        4 => Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Dogleg)),
+        5 => Box::new(BspDungeonBuilder::new(
+            depth,
+            CorridorStyle::NearestNeighbour,
+        )),
+        6 => Box::new(WaveformCollapseBuilder::new(
+            depth,
+            Box::new(CellularAutomataBuilder::new(depth)),
+        )),
+        _ => {
+            let level = rng.random_slice_entry(LEVELS).expect("no prefab levels");
+
+            Box::new(WaveformCollapseBuilder::new(
+                depth,
+                Box::new(PrefabBuilder::level(depth, level)),
+            ))
+        }
+    };
+
+    if rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(TerrainBuilder::new(depth, builder));
+    }
+
+    if rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(PrefabBuilder::vaults(depth, builder));
+    }
+
+    if rng.roll_dice(1, 4) == 1 {
+        builder = Box::new(PrefabBuilder::shop(depth, builder));
+    }
+
+    if rng.roll_dice(1, 5) == 1 {
+        let section = rng
+            .random_slice_entry(SECTIONS)
+            .expect("no prefab sections");
+
+        builder = Box::new(PrefabBuilder::sectional(depth, section, builder));
+    }
+
+    builder
+}
+
+/// The hand-drawn level used when random generation keeps failing; it always passes validation
+pub fn fallback_builder(depth: i32) -> Box<dyn MapBuilder> {
+    Box::new(PrefabBuilder::level(depth, &LEVELS[0]))
+}
+
+/// Generates levels for the given depth until one passes validation, and returns its builder ready to spawn from.
+/// Should every attempt fail, the player gets the fallback level rather than a crash
+pub fn build_level(depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    for _ in 0..MAX_GENERATION_ATTEMPTS {
+        let mut builder = random_builder(depth, rng);
+        builder.build_map(rng);
+
+        if validate_level(&builder.get_map(), &builder.get_starting_position()).is_ok() {
+            return builder;
+        }
+    }
+
+    let mut builder = fallback_builder(depth);
+    builder.build_map(rng);
+    builder
+}
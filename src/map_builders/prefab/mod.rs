@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::{Player, Position},
+    map::{CellType, Map},
+    spawn,
+};
+
+mod templates;
+
+pub use templates::{
+    HorizontalPlacement, PrefabLevel, PrefabRoom, PrefabSection, VerticalPlacement, LEVELS,
+    SECTIONS, SHOPS, VAULTS,
+};
+
+// What each spawning template glyph resolves to in the spawn system
+const GLYPH_SPAWNS: &[(char, &str)] = &[
+    ('g', "Goblin"),
+    ('o', "Orc"),
+    ('r', "Rat"),
+    ('!', "Health Potion"),
+    ('?', "Magic Missile Scroll"),
+    ('*', "Fireball Scroll"),
+    ('^', "Spike Trap"),
+    ('$', "Gold"),
+    ('S', "Shopkeeper"),
+];
+
+enum PrefabMode {
+    Level(&'static PrefabLevel),
+    Sectional(&'static PrefabSection),
+    Rooms(&'static [PrefabRoom]),
+}
+
+/// Stamps hand-drawn templates into a level, either as the whole level or on top of another generator's level
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    mode: PrefabMode,
+    previous_builder: Option<Box<dyn MapBuilder>>,
+    spawns: Vec<(usize, &'static str)>,
+    stamped: HashSet<usize>,
+    history: Vec<Vec<CellType>>,
+}
+
+impl PrefabBuilder {
+    fn new(
+        depth: i32,
+        mode: PrefabMode,
+        previous_builder: Option<Box<dyn MapBuilder>>,
+    ) -> PrefabBuilder {
+        PrefabBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth,
+            mode,
+            previous_builder,
+            spawns: Vec::new(),
+            stamped: HashSet::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// A level drawn entirely from the template
+    pub fn level(depth: i32, level: &'static PrefabLevel) -> PrefabBuilder {
+        PrefabBuilder::new(depth, PrefabMode::Level(level), None)
+    }
+
+    /// The previous builder's level with the section stamped over part of it
+    pub fn sectional(
+        depth: i32,
+        section: &'static PrefabSection,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> PrefabBuilder {
+        PrefabBuilder::new(
+            depth,
+            PrefabMode::Sectional(section),
+            Some(previous_builder),
+        )
+    }
+
+    /// The previous builder's level with a vault dropped into one of its rooms
+    pub fn vaults(depth: i32, previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(depth, PrefabMode::Rooms(VAULTS), Some(previous_builder))
+    }
+
+    /// The previous builder's level with a shop set up in one of its rooms
+    pub fn shop(depth: i32, previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(depth, PrefabMode::Rooms(SHOPS), Some(previous_builder))
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+
+    /// Reads a template into rows of glyphs, dropping the blank lines around it
+    fn read_template(template: &str) -> Vec<Vec<char>> {
+        template
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect()
+    }
+
+    /// The width and height of a template read by read_template
+    fn template_size(rows: &[Vec<char>]) -> (i32, i32) {
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        (width as i32, rows.len() as i32)
+    }
+
+    /// Copies the template onto the map with its top left corner at x, y
+    fn stamp(&mut self, rows: &[Vec<char>], x: i32, y: i32) {
+        for (dy, row) in rows.iter().enumerate() {
+            for (dx, glyph) in row.iter().enumerate() {
+                let cell_x = x + dx as i32;
+                let cell_y = y + dy as i32;
+                let idx = self.map.xy_idx(cell_x, cell_y);
+
+                self.map.cells[idx] = match glyph {
+                    '#' => CellType::Wall,
+                    '+' => CellType::ClosedDoor,
+                    '>' => CellType::DownStairs,
+                    _ => CellType::Floor,
+                };
+
+                if *glyph == '@' {
+                    self.starting_position = Position {
+                        x: cell_x,
+                        y: cell_y,
+                    };
+                }
+
+                if let Some((_, name)) = GLYPH_SPAWNS.iter().find(|(g, _)| g == glyph) {
+                    self.spawns.push((idx, name));
+                }
+
+                self.stamped.insert(idx);
+            }
+        }
+    }
+
+    fn build_level(&mut self, level: &PrefabLevel) {
+        let rows = PrefabBuilder::read_template(level.template);
+        let (width, height) = PrefabBuilder::template_size(&rows);
+
+        self.map = Map::new(self.depth, width, height);
+        self.stamp(&rows, 0, 0);
+    }
+
+    fn apply_section(&mut self, section: &PrefabSection) {
+        let rows = PrefabBuilder::read_template(section.template);
+        let (width, height) = PrefabBuilder::template_size(&rows);
+
+        // Keep clear of the map's outer wall
+        let x = match section.placement.0 {
+            HorizontalPlacement::Left => 1,
+            HorizontalPlacement::Center => (self.map.width - width) / 2,
+            HorizontalPlacement::Right => self.map.width - width - 1,
+        };
+        let y = match section.placement.1 {
+            VerticalPlacement::Top => 1,
+            VerticalPlacement::Center => (self.map.height - height) / 2,
+            VerticalPlacement::Bottom => self.map.height - height - 1,
+        };
+
+        self.stamp(&rows, x, y);
+    }
+
+    /// Drops one of the rooms suited to this depth into a room of the level
+    fn apply_room(&mut self, rng: &mut RandomNumberGenerator, prefabs: &[PrefabRoom]) {
+        let candidates: Vec<&PrefabRoom> = prefabs
+            .iter()
+            .filter(|prefab| self.depth >= prefab.first_depth && self.depth <= prefab.last_depth)
+            .collect();
+
+        let prefab = match rng.random_slice_entry(&candidates) {
+            Some(prefab) => *prefab,
+            None => return,
+        };
+
+        let rows = PrefabBuilder::read_template(prefab.template);
+        let (width, height) = PrefabBuilder::template_size(&rows);
+        let stairs_idx = self
+            .map
+            .cells
+            .iter()
+            .position(|cell| *cell == CellType::DownStairs);
+
+        // They stay out of the starting room and the room with the stairs
+        let rooms: Vec<_> = self
+            .map
+            .rooms
+            .iter()
+            .skip(1)
+            .filter(|room| room.x2 - room.x1 >= width && room.y2 - room.y1 >= height)
+            .filter(|room| {
+                stairs_idx.is_none_or(|idx| {
+                    let x = idx as i32 % self.map.width;
+                    let y = idx as i32 / self.map.width;
+
+                    x <= room.x1 || x > room.x2 || y <= room.y1 || y > room.y2
+                })
+            })
+            .cloned()
+            .collect();
+
+        // Levels without rooms, or rooms too small, just go without
+        let room = match rng.random_slice_entry(&rooms) {
+            Some(room) => *room,
+            None => return,
+        };
+
+        let x = room.x1 + 1 + rng.range(0, room.x2 - room.x1 - width + 1);
+        let y = room.y1 + 1 + rng.range(0, room.y2 - room.y1 - height + 1);
+
+        self.stamp(&rows, x, y);
+    }
+
+    /// Makes sure stamping over the previous level hasn't buried the start or cut off the stairs. Leaves a level
+    /// with no floor outside the templates as it is, for validation to turn down
+    fn reconnect(&mut self) {
+        let mut start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        if self.map.cells[start_idx] != CellType::Floor {
+            let start = Point::new(self.starting_position.x, self.starting_position.y);
+            let width = self.map.width;
+
+            let nearest_floor = self
+                .map
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(idx, cell)| **cell == CellType::Floor && !self.stamped.contains(idx))
+                .map(|(idx, _)| {
+                    let cell = Point::new(idx as i32 % width, idx as i32 / width);
+                    (idx, rltk::DistanceAlg::Pythagoras.distance2d(start, cell))
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .map(|(idx, _)| idx);
+
+            // With no floor left to start on, the level fails validation and gets generated again
+            start_idx = match nearest_floor {
+                Some(idx) => idx,
+                None => return,
+            };
+
+            self.starting_position = Position {
+                x: start_idx as i32 % width,
+                y: start_idx as i32 / width,
+            };
+        }
+
+        let stairs_idx = self
+            .map
+            .cells
+            .iter()
+            .position(|cell| *cell == CellType::DownStairs);
+
+        if let Some(idx) = stairs_idx {
+            self.map.cells[idx] = CellType::Floor;
+        }
+
+        let farthest_idx = common::cull_unreachable(&mut self.map, start_idx);
+
+        // Stairs that were walled off move to the far end of what's left
+        match stairs_idx {
+            Some(idx) if self.map.cells[idx] == CellType::Floor => {
+                self.map.cells[idx] = CellType::DownStairs;
+            }
+            _ => self.map.cells[farthest_idx] = CellType::DownStairs,
+        }
+    }
+
+    /// Deletes whatever the previous builder spawned inside a template or in a cell that's since been walled off
+    fn clear_stamped_spawns(&self, ecs: &mut World) {
+        let doomed: Vec<Entity> = {
+            let entities = ecs.entities();
+            let positions = ecs.read_storage::<Position>();
+            let players = ecs.read_storage::<Player>();
+
+            (&entities, &positions, !&players)
+                .join()
+                .filter(|(_, pos, _)| {
+                    let idx = self.map.xy_idx(pos.x, pos.y);
+                    self.stamped.contains(&idx) || self.map.cells[idx] == CellType::Wall
+                })
+                .map(|(entity, _, _)| entity)
+                .collect()
+        };
+
+        ecs.delete_entities(&doomed)
+            .expect("unable to delete entities under prefab");
+    }
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        if let Some(previous) = self.previous_builder.as_mut() {
+            previous.build_map(rng);
+            self.map = previous.get_map();
+            self.starting_position = previous.get_starting_position();
+            self.history = previous.get_snapshot_history();
+        }
+
+        match self.mode {
+            PrefabMode::Level(level) => self.build_level(level),
+            PrefabMode::Sectional(section) => {
+                self.apply_section(section);
+                self.take_snapshot();
+                self.reconnect();
+            }
+            PrefabMode::Rooms(prefabs) => {
+                self.apply_room(rng, prefabs);
+                self.take_snapshot();
+                self.reconnect();
+            }
+        }
+
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        if let Some(previous) = self.previous_builder.as_mut() {
+            previous.spawn_entities(ecs);
+            self.clear_stamped_spawns(ecs);
+        }
+
+        for (idx, name) in self.spawns.iter() {
+            let x = *idx as i32 % self.map.width;
+            let y = *idx as i32 / self.map.width;
+
+            spawn::spawn_entity(ecs, x, y, name);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
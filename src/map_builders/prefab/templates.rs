@@ -0,0 +1,167 @@
+// Template glyphs:
+//   #  wall             .  floor          +  closed door     >  down stairs
+//   @  starting point   g  goblin         o  orc             r  rat
+//   !  health potion    ?  magic missile  *  fireball        ^  spike trap
+//   $  gold             S  shopkeeper
+
+/// A hand-drawn map that replaces the whole level
+pub struct PrefabLevel {
+    pub template: &'static str,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum HorizontalPlacement {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum VerticalPlacement {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// A hand-drawn piece stamped onto a generated level at a fixed edge or the middle
+pub struct PrefabSection {
+    pub template: &'static str,
+    pub placement: (HorizontalPlacement, VerticalPlacement),
+}
+
+/// A hand-drawn room dropped inside a generated room that is big enough to hold it
+pub struct PrefabRoom {
+    pub template: &'static str,
+    pub first_depth: i32,
+    pub last_depth: i32,
+}
+
+pub const LEVELS: &[PrefabLevel] = &[PrefabLevel { template: FORTRESS }];
+
+pub const SECTIONS: &[PrefabSection] = &[
+    PrefabSection {
+        template: GUARD_POST,
+        placement: (HorizontalPlacement::Right, VerticalPlacement::Top),
+    },
+    PrefabSection {
+        template: RAT_WARREN,
+        placement: (HorizontalPlacement::Left, VerticalPlacement::Bottom),
+    },
+];
+
+pub const VAULTS: &[PrefabRoom] = &[
+    PrefabRoom {
+        template: TOTALLY_NOT_A_TRAP,
+        first_depth: 0,
+        last_depth: 100,
+    },
+    PrefabRoom {
+        template: ARMOURY,
+        first_depth: 2,
+        last_depth: 100,
+    },
+    PrefabRoom {
+        template: ORC_DEN,
+        first_depth: 3,
+        last_depth: 100,
+    },
+];
+
+pub const SHOPS: &[PrefabRoom] = &[
+    PrefabRoom {
+        template: GENERAL_STORE,
+        first_depth: 0,
+        last_depth: 100,
+    },
+    PrefabRoom {
+        template: BACK_ROOM_DEALER,
+        first_depth: 3,
+        last_depth: 100,
+    },
+];
+
+const FORTRESS: &str = "
+##############################################
+#@.......#...........................#.......#
+#........#...........................#...?...#
+#........+...........g...............+.......#
+#........#...........................#.......#
+#........#.........#######...........#.......#
+######+###.........#..!..#...........###+#####
+#........#.........#.....#...........#.......#
+#..r.....#.........###+###.....o.....#.......#
+#........#...........................#.......#
+#........+...........................#...>...#
+#........#...........................#.......#
+##########################+###################
+#.............................#..............#
+#...^.....g...................+......*.......#
+#.............................#..............#
+##############################################
+";
+
+const GUARD_POST: &str = "
+###########
+#.........#
+#...o.....#
+#.........#
+#....!....#
+#.........#
+#####.#####
+";
+
+const RAT_WARREN: &str = "
+.............
+.###.###.###.
+.#r#.#.#.#r#.
+.#...#r#...#.
+.#.#.....#.#.
+.#r#.#.#.#.#.
+.###.###.###.
+.............
+";
+
+const TOTALLY_NOT_A_TRAP: &str = "
+.....
+.^^^.
+.^!^.
+.^^^.
+.....
+";
+
+const ARMOURY: &str = "
+.......
+.#####.
+.#?.*#.
+.#...#.
+.##+##.
+.......
+";
+
+const ORC_DEN: &str = "
+.......
+.o...o.
+...!...
+.o...o.
+.......
+";
+
+const GENERAL_STORE: &str = "
+.........
+.#######.
+.#..S..#.
+.#.....#.
+.###+###.
+.........
+";
+
+const BACK_ROOM_DEALER: &str = "
+.......
+.#####.
+.#S.$#.
+.#...#.
+.##+##.
+.#...+.
+.#####.
+.......
+";
@@ -0,0 +1,118 @@
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::Position,
+    geometry::Rect,
+    map::{CellType, Map},
+    spawn,
+};
+
+// The dimensions of maps made by the rooms and tunnels generator
+const ROOMS_MAP_WIDTH: i32 = 100;
+const ROOMS_MAP_HEIGHT: i32 = 60;
+
+// The max number of rooms to generate
+const MAX_ROOMS: i32 = 30;
+
+// Rand ranges
+const MIN_SIZE: i32 = 6;
+const MAX_SIZE: i32 = 10;
+
+/// Rectangular rooms joined by L-shaped tunnels
+pub struct RoomsAndTunnelsBuilder {
+    map: Map,
+    depth: i32,
+    history: Vec<Vec<CellType>>,
+}
+
+impl RoomsAndTunnelsBuilder {
+    pub fn new(depth: i32) -> RoomsAndTunnelsBuilder {
+        RoomsAndTunnelsBuilder {
+            map: Map::default(),
+            depth,
+            history: Vec::new(),
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+}
+
+impl MapBuilder for RoomsAndTunnelsBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.map = Map::new(self.depth, ROOMS_MAP_WIDTH, ROOMS_MAP_HEIGHT);
+
+        for _ in 0..MAX_ROOMS {
+            // Generate random width and height
+            let w = rng.range(MIN_SIZE, MAX_SIZE);
+            let h = rng.range(MIN_SIZE, MAX_SIZE);
+
+            // Generate the room center
+            let x = rng.roll_dice(1, self.map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.map.height - h - 1) - 1;
+
+            // Given w/h and x/y, generate the room
+            let new_room = Rect::new(x, y, w, h);
+
+            // If the room doesn't overlap with any of the others we've generated...
+            if !self
+                .map
+                .rooms
+                .iter()
+                .any(|other_room| new_room.intersects(other_room))
+            {
+                self.map.apply_room_to_map(&new_room);
+
+                if !self.map.rooms.is_empty() {
+                    let (new_x, new_y) = new_room.center();
+                    let (prev_x, prev_y) = self.map.rooms[self.map.rooms.len() - 1].center();
+
+                    // Place tunnels between this room and the previous room
+                    if rng.range(0, 2) == 1 {
+                        self.map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
+                        self.map.apply_vertical_tunnel(prev_y, new_y, new_x);
+                    } else {
+                        self.map.apply_vertical_tunnel(prev_y, new_y, prev_x);
+                        self.map.apply_horizontal_tunnel(prev_x, new_x, new_y);
+                    }
+                }
+
+                self.map.rooms.push(new_room);
+                self.take_snapshot();
+            }
+        }
+
+        self.map.apply_doors();
+
+        // Stairs go as far from the start as can be walked
+        let start = self.get_starting_position();
+        let start_idx = self.map.xy_idx(start.x, start.y);
+        let stairs_idx = common::cull_unreachable(&mut self.map, start_idx);
+        self.map.cells[stairs_idx] = CellType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        // Skip room player spawned in
+        for room in self.map.rooms.iter().skip(1) {
+            spawn::room(ecs, room, self.depth);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        let (x, y) = self.map.rooms[0].center();
+
+        Position { x, y }
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
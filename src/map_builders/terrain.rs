@@ -0,0 +1,281 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::{Player, Position},
+    map::{CellType, Map},
+};
+
+// Lakes below this depth may be lava instead of water
+const LAVA_MIN_DEPTH: i32 = 4;
+
+// Rand range for a lake's radius
+const MIN_LAKE_RADIUS: i32 = 3;
+const MAX_LAKE_RADIUS: i32 = 7;
+
+// How many patches of grass and rubble are scattered over the level
+const GRASS_PATCHES: i32 = 6;
+const RUBBLE_PATCHES: i32 = 4;
+
+/// Runs rivers and lakes through the previous builder's level, and scatters grass and rubble over its floor
+pub struct TerrainBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    previous_builder: Box<dyn MapBuilder>,
+    history: Vec<Vec<CellType>>,
+}
+
+impl TerrainBuilder {
+    pub fn new(depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth,
+            previous_builder,
+            history: Vec::new(),
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+
+    /// Whether terrain may be laid at x, y. The edge of the map, the start and the cells around it, the stairs and
+    /// doors are all left alone
+    fn can_paint(&self, x: i32, y: i32) -> bool {
+        if x < 1 || x >= self.map.width - 1 || y < 1 || y >= self.map.height - 1 {
+            return false;
+        }
+
+        let start = Point::new(self.starting_position.x, self.starting_position.y);
+        if rltk::DistanceAlg::Chebyshev.distance2d(start, Point::new(x, y)) <= 1.0 {
+            return false;
+        }
+
+        !matches!(
+            self.map.cells[self.map.xy_idx(x, y)],
+            CellType::DownStairs | CellType::ClosedDoor | CellType::OpenDoor
+        )
+    }
+
+    fn paint(&mut self, x: i32, y: i32, cell: CellType) {
+        if self.can_paint(x, y) {
+            let idx = self.map.xy_idx(x, y);
+            self.map.cells[idx] = cell;
+        }
+    }
+
+    /// Whether x, y is dry ground something could stand on
+    fn is_land(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= self.map.width || y < 0 || y >= self.map.height {
+            return false;
+        }
+
+        matches!(
+            self.map.cells[self.map.xy_idx(x, y)],
+            CellType::Floor | CellType::Grass | CellType::OpenDoor | CellType::ClosedDoor
+        )
+    }
+
+    /// A river wandering from the top of the map to the bottom: deep in the middle, shallow at the banks, and
+    /// bridged wherever it cuts across a room or corridor
+    fn apply_river(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut x = rng.range(3, self.map.width - 3);
+
+        for y in 1..self.map.height - 1 {
+            x = (x + rng.range(-1, 2)).clamp(2, self.map.width - 3);
+
+            let bridged = self.is_land(x - 2, y) && self.is_land(x + 2, y);
+
+            for dx in -1..=1 {
+                let cell = if bridged {
+                    CellType::Bridge
+                } else if dx == 0 {
+                    CellType::DeepWater
+                } else {
+                    CellType::ShallowWater
+                };
+
+                self.paint(x + dx, y, cell);
+            }
+        }
+    }
+
+    /// A round lake of the given liquid on a random patch of floor. Water lakes are ringed by shallows then
+    /// grass, lava lakes by rubble
+    fn apply_lake(&mut self, rng: &mut RandomNumberGenerator, liquid: CellType) {
+        let floor: Vec<usize> = self
+            .map
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == CellType::Floor)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let center_idx = match rng.random_slice_entry(&floor) {
+            Some(idx) => *idx,
+            None => return,
+        };
+        let center = Point::new(
+            center_idx as i32 % self.map.width,
+            center_idx as i32 / self.map.width,
+        );
+        let radius = rng.range(MIN_LAKE_RADIUS, MAX_LAKE_RADIUS);
+        let shore = if liquid == CellType::Lava {
+            CellType::Rubble
+        } else {
+            CellType::ShallowWater
+        };
+
+        for y in center.y - radius - 1..=center.y + radius + 1 {
+            for x in center.x - radius - 1..=center.x + radius + 1 {
+                // A little noise keeps the edge from looking drawn with a compass
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(center, Point::new(x, y))
+                    + rng.range(0, 2) as f32 * 0.5;
+
+                if distance < radius as f32 - 1.5 {
+                    self.paint(x, y, liquid);
+                } else if distance < radius as f32 {
+                    self.paint(x, y, shore);
+                } else if distance < radius as f32 + 1.5
+                    && liquid != CellType::Lava
+                    && self.is_land(x, y)
+                {
+                    self.paint(x, y, CellType::Grass);
+                }
+            }
+        }
+    }
+
+    /// Turns a few small clumps of floor into the given cell
+    fn scatter(&mut self, rng: &mut RandomNumberGenerator, cell: CellType, patches: i32) {
+        for _ in 0..patches {
+            let x = rng.range(1, self.map.width - 1);
+            let y = rng.range(1, self.map.height - 1);
+
+            for _ in 0..rng.range(4, 12) {
+                let px = x + rng.range(-2, 3);
+                let py = y + rng.range(-2, 3);
+
+                if self.is_land(px, py)
+                    && self.map.cells[self.map.xy_idx(px, py)] == CellType::Floor
+                {
+                    self.paint(px, py, cell);
+                }
+            }
+        }
+    }
+
+    /// Whether everywhere that could be walked to before, the stairs included, can still be reached without
+    /// wading through lava
+    fn is_safe_without_lava(&self, before: &[CellType]) -> bool {
+        let mut map = self.map.clone();
+        map.populate_blocked();
+        map.set_closed_doors_blocked(false);
+
+        for (idx, cell) in map.cells.iter().enumerate() {
+            if *cell == CellType::Lava {
+                map.blocked[idx] = true;
+            }
+        }
+
+        let start_idx = map.xy_idx(self.starting_position.x, self.starting_position.y);
+        let dijkstra = rltk::DijkstraMap::new(
+            map.width,
+            map.height,
+            &[start_idx],
+            &map,
+            common::DIJKSTRA_MAX_DEPTH,
+        );
+
+        map.cells.iter().enumerate().all(|(idx, cell)| {
+            !before[idx].is_walkable() || *cell == CellType::Lava || dijkstra.map[idx] != f32::MAX
+        })
+    }
+
+    /// Deletes anything the previous builder spawned into lava, deep water or rock
+    fn clear_drowned_spawns(&self, ecs: &mut World) {
+        let doomed: Vec<Entity> = {
+            let entities = ecs.entities();
+            let positions = ecs.read_storage::<Position>();
+            let players = ecs.read_storage::<Player>();
+
+            (&entities, &positions, !&players)
+                .join()
+                .filter(|(_, pos, _)| {
+                    matches!(
+                        self.map.cells[self.map.xy_idx(pos.x, pos.y)],
+                        CellType::Wall | CellType::Lava | CellType::DeepWater
+                    )
+                })
+                .map(|(entity, _, _)| entity)
+                .collect()
+        };
+
+        ecs.delete_entities(&doomed)
+            .expect("unable to delete entities under terrain");
+    }
+}
+
+impl MapBuilder for TerrainBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.map = self.previous_builder.get_map();
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        // Even odds of a river, and separately of a lake
+        if rng.roll_dice(1, 2) == 1 {
+            self.apply_river(rng);
+            common::cull_unreachable(&mut self.map, start_idx);
+            self.take_snapshot();
+        }
+
+        if rng.roll_dice(1, 2) == 1 {
+            let liquid = if self.depth >= LAVA_MIN_DEPTH && rng.roll_dice(1, 3) == 1 {
+                CellType::Lava
+            } else {
+                CellType::DeepWater
+            };
+
+            let before = self.map.cells.clone();
+            self.apply_lake(rng, liquid);
+
+            // Lava that cuts the level in two would have to be walked through, so it isn't kept
+            if !self.is_safe_without_lava(&before) {
+                self.map.cells = before;
+            }
+            self.take_snapshot();
+        }
+
+        self.scatter(rng, CellType::Grass, GRASS_PATCHES);
+        self.scatter(rng, CellType::Rubble, RUBBLE_PATCHES);
+
+        common::cull_unreachable(&mut self.map, start_idx);
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        self.previous_builder.spawn_entities(ecs);
+        self.clear_drowned_spawns(ecs);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
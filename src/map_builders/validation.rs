@@ -0,0 +1,85 @@
+use super::common::DIJKSTRA_MAX_DEPTH;
+use crate::{
+    components::Position,
+    map::{CellType, Map},
+};
+
+// Levels built from rooms need at least this many of them
+const MIN_ROOMS: usize = 4;
+
+// The smallest share of the map, out of 100, that may be floor
+const MIN_FLOOR_PERCENT: usize = 10;
+
+/// Why a generated level isn't fit to play
+#[derive(PartialEq, Debug)]
+pub enum LevelProblem {
+    StartNotFloor,
+    UnreachableCell { x: i32, y: i32 },
+    NoStairs,
+    StairsAtStart,
+    UnreachableStairs,
+    TooFewRooms(usize),
+    TooLittleFloor { percent: usize },
+}
+
+/// Checks that every walkable cell and the stairs can be reached from the start through the map's exits, and
+/// that there's enough level to play on
+pub fn validate_level(map: &Map, start: &Position) -> Result<(), LevelProblem> {
+    let start_idx = map.xy_idx(start.x, start.y);
+    if map.cells[start_idx] != CellType::Floor {
+        return Err(LevelProblem::StartNotFloor);
+    }
+
+    if !map.rooms.is_empty() && map.rooms.len() < MIN_ROOMS {
+        return Err(LevelProblem::TooFewRooms(map.rooms.len()));
+    }
+
+    let floor = map
+        .cells
+        .iter()
+        .filter(|cell| **cell == CellType::Floor)
+        .count();
+    let percent = floor * 100 / map.cells.len();
+
+    if percent < MIN_FLOOR_PERCENT {
+        return Err(LevelProblem::TooLittleFloor { percent });
+    }
+
+    // Doors count as open; anyone can walk through them
+    let mut map = map.clone();
+    map.populate_blocked();
+    map.set_closed_doors_blocked(false);
+
+    let dijkstra = rltk::DijkstraMap::new(
+        map.width,
+        map.height,
+        &[start_idx],
+        &map,
+        DIJKSTRA_MAX_DEPTH,
+    );
+
+    let stairs_idx = map
+        .cells
+        .iter()
+        .position(|cell| *cell == CellType::DownStairs)
+        .ok_or(LevelProblem::NoStairs)?;
+
+    if stairs_idx == start_idx {
+        return Err(LevelProblem::StairsAtStart);
+    }
+
+    if dijkstra.map[stairs_idx] == f32::MAX {
+        return Err(LevelProblem::UnreachableStairs);
+    }
+
+    for (idx, cell) in map.cells.iter().enumerate() {
+        if cell.is_walkable() && dijkstra.map[idx] == f32::MAX {
+            return Err(LevelProblem::UnreachableCell {
+                x: idx as i32 % map.width,
+                y: idx as i32 / map.width,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{common, MapBuilder};
+use crate::{
+    components::Position,
+    map::{CellType, Map},
+    spawn,
+};
+
+mod patterns;
+mod solver;
+
+use patterns::PatternSet;
+use solver::Solver;
+
+const WFC_MAP_WIDTH: i32 = 80;
+const WFC_MAP_HEIGHT: i32 = 50;
+
+// The width and height of the squares the sample is cut into
+const CHUNK_SIZE: i32 = 8;
+
+// How many maps are tried before falling back on the sample itself
+const MAX_ATTEMPTS: i32 = 20;
+
+// The share of the map, out of 100, that must be reachable floor for a map to be kept
+const MIN_FLOOR_PERCENT: usize = 20;
+
+// The width and height of the regions spawns are spread over
+const REGION_SIZE: i32 = 16;
+
+/// New levels in the style of a sample level, grown chunk by chunk so neighbouring chunks always line up
+pub struct WaveformCollapseBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    sample_builder: Box<dyn MapBuilder>,
+    regions: HashMap<i32, Vec<usize>>,
+    history: Vec<Vec<CellType>>,
+}
+
+impl WaveformCollapseBuilder {
+    /// Learns from whatever the sample builder generates, be it another generator or a prefab level
+    pub fn new(depth: i32, sample_builder: Box<dyn MapBuilder>) -> WaveformCollapseBuilder {
+        WaveformCollapseBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth,
+            sample_builder,
+            regions: HashMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(self.map.cells.clone());
+    }
+
+    /// Fills the map with the chosen patterns and closes off the outer edge
+    fn render(&mut self, patterns: &PatternSet, chosen: &[usize]) {
+        let chunks_per_row = self.map.width / patterns.chunk_size;
+
+        for (chunk_idx, pattern) in chosen.iter().enumerate() {
+            let chunk_x = chunk_idx as i32 % chunks_per_row;
+            let chunk_y = chunk_idx as i32 / chunks_per_row;
+
+            for (cell_idx, cell) in patterns.patterns[*pattern].iter().enumerate() {
+                let x = chunk_x * patterns.chunk_size + cell_idx as i32 % patterns.chunk_size;
+                let y = chunk_y * patterns.chunk_size + cell_idx as i32 / patterns.chunk_size;
+                let idx = self.map.xy_idx(x, y);
+
+                self.map.cells[idx] = *cell;
+            }
+        }
+
+        for y in 0..self.map.height {
+            for x in 0..self.map.width {
+                if x == 0 || y == 0 || x == self.map.width - 1 || y == self.map.height - 1 {
+                    let idx = self.map.xy_idx(x, y);
+                    self.map.cells[idx] = CellType::Wall;
+                }
+            }
+        }
+    }
+
+    /// Starts on the floor nearest the middle of the map
+    fn find_starting_position(&mut self) -> Option<usize> {
+        let middle = Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = common::nearest_floor(&self.map, middle)?;
+
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        Some(start_idx)
+    }
+
+    /// Generates a map from the patterns, culls whatever can't be reached from the start through the map's exits,
+    /// and places the stairs. Returns false if too little of the map is left to play on
+    fn try_generate(&mut self, patterns: &PatternSet, rng: &mut RandomNumberGenerator) -> bool {
+        let mut solver = Solver::new(
+            patterns,
+            WFC_MAP_WIDTH / CHUNK_SIZE,
+            WFC_MAP_HEIGHT / CHUNK_SIZE,
+        );
+
+        if !solver.solve(rng) {
+            return false;
+        }
+
+        self.map = Map::new(self.depth, WFC_MAP_WIDTH, WFC_MAP_HEIGHT);
+        self.render(patterns, &solver.chosen());
+        self.take_snapshot();
+
+        let start_idx = match self.find_starting_position() {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        let stairs_idx = common::cull_unreachable(&mut self.map, start_idx);
+
+        let floor = self
+            .map
+            .cells
+            .iter()
+            .filter(|cell| **cell == CellType::Floor)
+            .count();
+
+        if floor * 100 < self.map.cells.len() * MIN_FLOOR_PERCENT {
+            return false;
+        }
+
+        self.map.cells[stairs_idx] = CellType::DownStairs;
+        self.take_snapshot();
+        true
+    }
+}
+
+impl MapBuilder for WaveformCollapseBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.sample_builder.build_map(rng);
+        let sample = self.sample_builder.get_map();
+        let patterns = patterns::learn_patterns(&sample, CHUNK_SIZE);
+
+        let generated = !patterns.patterns.is_empty()
+            && (0..MAX_ATTEMPTS).any(|_| self.try_generate(&patterns, rng));
+
+        // A sample too small or too odd to learn from is played as it is
+        if !generated {
+            self.map = sample;
+            self.starting_position = self.sample_builder.get_starting_position();
+            self.history = self.sample_builder.get_snapshot_history();
+        }
+
+        self.regions = common::floor_regions(&self.map, REGION_SIZE);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        // Skip region player spawned in
+        for area in self.regions.values() {
+            if !area.contains(&start_idx) {
+                spawn::region(ecs, area, self.depth);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<CellType>> {
+        self.history.clone()
+    }
+}
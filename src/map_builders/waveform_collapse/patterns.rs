@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::map::{CellType, Map};
+
+// Offsets to the neighbouring chunk above, below, left and right. Opposite directions differ only in the lowest
+// bit, so `dir ^ 1` flips a direction
+pub const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// The square chunks of a sample map, with how often each appeared and which may sit next to which
+pub struct PatternSet {
+    pub chunk_size: i32,
+    pub patterns: Vec<Vec<CellType>>,
+    pub weights: Vec<u32>,
+    // For each pattern and direction, the patterns that may sit beside it in that direction
+    pub compatible: Vec<[Vec<usize>; 4]>,
+}
+
+/// Cuts the sample into chunk_size squares, along with their mirror images, and works out which chunks fit
+/// together by matching the cells along their shared edges
+pub fn learn_patterns(sample: &Map, chunk_size: i32) -> PatternSet {
+    let mut counts: HashMap<Vec<CellType>, u32> = HashMap::new();
+    let mut order: Vec<Vec<CellType>> = Vec::new();
+
+    for chunk_y in 0..sample.height / chunk_size {
+        for chunk_x in 0..sample.width / chunk_size {
+            for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+                let pattern = read_chunk(sample, chunk_x, chunk_y, chunk_size, flip_x, flip_y);
+
+                let count = counts.entry(pattern.clone()).or_insert(0);
+                if *count == 0 {
+                    order.push(pattern);
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    let weights = order.iter().map(|pattern| counts[pattern]).collect();
+    let compatible = order
+        .iter()
+        .map(|pattern| {
+            let mut neighbours: [Vec<usize>; 4] = Default::default();
+
+            for (dir, allowed) in neighbours.iter_mut().enumerate() {
+                let own_edge = edge(pattern, chunk_size, dir);
+
+                *allowed = order
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| edge(other, chunk_size, dir ^ 1) == own_edge)
+                    .map(|(idx, _)| idx)
+                    .collect();
+            }
+
+            neighbours
+        })
+        .collect();
+
+    PatternSet {
+        chunk_size,
+        patterns: order,
+        weights,
+        compatible,
+    }
+}
+
+/// Copies one chunk out of the sample, optionally mirrored. Stairs are read as floor; the builder places its own
+fn read_chunk(
+    sample: &Map,
+    chunk_x: i32,
+    chunk_y: i32,
+    chunk_size: i32,
+    flip_x: bool,
+    flip_y: bool,
+) -> Vec<CellType> {
+    let mut pattern = Vec::with_capacity((chunk_size * chunk_size) as usize);
+
+    for y in 0..chunk_size {
+        for x in 0..chunk_size {
+            let sample_x = chunk_x * chunk_size + if flip_x { chunk_size - 1 - x } else { x };
+            let sample_y = chunk_y * chunk_size + if flip_y { chunk_size - 1 - y } else { y };
+
+            pattern.push(match sample.cells[sample.xy_idx(sample_x, sample_y)] {
+                CellType::DownStairs => CellType::Floor,
+                cell => cell,
+            });
+        }
+    }
+
+    pattern
+}
+
+/// The row or column of cells along one side of a pattern
+fn edge(pattern: &[CellType], chunk_size: i32, dir: usize) -> Vec<CellType> {
+    let last = chunk_size - 1;
+
+    (0..chunk_size)
+        .map(|i| {
+            let (x, y) = match dir {
+                0 => (i, 0),
+                1 => (i, last),
+                2 => (0, i),
+                _ => (last, i),
+            };
+
+            pattern[(y * chunk_size + x) as usize]
+        })
+        .collect()
+}
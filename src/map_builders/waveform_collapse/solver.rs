@@ -0,0 +1,121 @@
+use rltk::RandomNumberGenerator;
+
+use super::patterns::{PatternSet, DIRECTIONS};
+
+/// Picks a pattern for every chunk of the output so that each fits all of its neighbours
+pub struct Solver<'a> {
+    patterns: &'a PatternSet,
+    width: i32,
+    height: i32,
+    // The patterns each chunk could still become
+    options: Vec<Vec<usize>>,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(patterns: &'a PatternSet, width: i32, height: i32) -> Solver<'a> {
+        let all: Vec<usize> = (0..patterns.patterns.len()).collect();
+
+        Solver {
+            patterns,
+            width,
+            height,
+            options: vec![all; (width * height) as usize],
+        }
+    }
+
+    /// Collapses the most constrained chunk, one at a time, until every chunk is decided. Returns false if the
+    /// choices so far leave some chunk with nothing that fits
+    pub fn solve(&mut self, rng: &mut RandomNumberGenerator) -> bool {
+        loop {
+            let fewest = self
+                .options
+                .iter()
+                .map(|options| options.len())
+                .filter(|len| *len > 1)
+                .min();
+
+            let fewest = match fewest {
+                Some(fewest) => fewest,
+                None => return true,
+            };
+
+            let undecided: Vec<usize> = self
+                .options
+                .iter()
+                .enumerate()
+                .filter(|(_, options)| options.len() == fewest)
+                .map(|(idx, _)| idx)
+                .collect();
+
+            let idx = *rng.random_slice_entry(&undecided).unwrap();
+            let choice = self.weighted_choice(idx, rng);
+            self.options[idx] = vec![choice];
+
+            if !self.propagate(idx) {
+                return false;
+            }
+        }
+    }
+
+    /// The pattern chosen for each chunk, left to right and top to bottom
+    pub fn chosen(&self) -> Vec<usize> {
+        self.options.iter().map(|options| options[0]).collect()
+    }
+
+    /// Picks one of the chunk's options, favouring the patterns that were common in the sample
+    fn weighted_choice(&self, idx: usize, rng: &mut RandomNumberGenerator) -> usize {
+        let weights = &self.patterns.weights;
+        let total: u32 = self.options[idx].iter().map(|p| weights[*p]).sum();
+        let mut roll = rng.range(0, total as i32) as u32;
+
+        for pattern in self.options[idx].iter() {
+            if roll < weights[*pattern] {
+                return *pattern;
+            }
+
+            roll -= weights[*pattern];
+        }
+
+        self.options[idx][0]
+    }
+
+    /// Strips options that no longer fit from the neighbours of a changed chunk, and from their neighbours in
+    /// turn. Returns false if a chunk is left with no options
+    fn propagate(&mut self, start: usize) -> bool {
+        let mut changed = vec![start];
+
+        while let Some(idx) = changed.pop() {
+            let x = idx as i32 % self.width;
+            let y = idx as i32 / self.width;
+
+            for (dir, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx >= self.width || ny < 0 || ny >= self.height {
+                    continue;
+                }
+
+                let mut allowed = vec![false; self.patterns.patterns.len()];
+                for pattern in self.options[idx].iter() {
+                    for neighbour in self.patterns.compatible[*pattern][dir].iter() {
+                        allowed[*neighbour] = true;
+                    }
+                }
+
+                let neighbour_idx = (ny * self.width + nx) as usize;
+                let before = self.options[neighbour_idx].len();
+                self.options[neighbour_idx].retain(|pattern| allowed[*pattern]);
+
+                let after = self.options[neighbour_idx].len();
+                if after == 0 {
+                    return false;
+                }
+
+                if after < before {
+                    changed.push(neighbour_idx);
+                }
+            }
+        }
+
+        true
+    }
+}
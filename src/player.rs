@@ -1,26 +1,35 @@
 use rltk::{self, Point};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
 use crate::{
     components::{
-        CombatStats, DesiresAcquireItem, DesiresMelee, Enemy, Item, Player, Position, Viewshed,
+        CombatStats, DesiresAcquireItem, DesiresMelee, Enemy, EntityMoved, Hidden, Item,
+        LightSource, Name, Player, Position, Shopkeeper, Viewshed,
     },
     log::GameLog,
-    map::{CellType, Map, MAP_HEIGHT, MAP_WIDTH},
+    map::{CellType, Map},
+    save,
     state::{RunState, State},
+    systems::ranged_combat,
+    ui,
 };
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+// How far from the player a search can uncover hidden things
+const SEARCH_RADIUS: f32 = 2.5;
+
+/// Moves the player, or attacks, opens or trades with whatever is in the way
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     let mut players = ecs.write_storage::<Player>();
     let mut positions = ecs.write_storage::<Position>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let shopkeepers = ecs.read_storage::<Shopkeeper>();
 
-    let map = ecs.fetch::<Map>();
+    let mut map = ecs.fetch_mut::<Map>();
 
     let entities = ecs.entities();
     let mut desires_melee = ecs.write_storage::<DesiresMelee>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
@@ -28,6 +37,13 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         let dest_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for maybe_target in map.cell_content[dest_idx].iter() {
+            if shopkeepers.get(*maybe_target).is_some() {
+                return RunState::Shop {
+                    shopkeeper: *maybe_target,
+                    mode: ui::ShopMode::Buy,
+                };
+            }
+
             let target = combat_stats.get(*maybe_target);
 
             if let Some(_target) = target {
@@ -39,21 +55,32 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("add DesiresMelee target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
 
+        // Bumping into a closed door opens it
+        if map.open_door(dest_idx) {
+            viewshed.dirty = true;
+            return RunState::PlayerTurn;
+        }
+
         if !map.blocked[dest_idx] {
-            pos.x = min(MAP_WIDTH - 1, max(0, pos.x + delta_x));
-            pos.y = min(MAP_HEIGHT - 1, max(0, pos.y + delta_y));
+            pos.x = (pos.x + delta_x).clamp(0, map.width - 1);
+            pos.y = (pos.y + delta_y).clamp(0, map.height - 1);
 
             viewshed.dirty = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("unable to insert moved marker");
 
             let mut player_pos = ecs.write_resource::<Point>();
             player_pos.x = pos.x;
             player_pos.y = pos.y;
         }
     }
+
+    RunState::PlayerTurn
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut rltk::Rltk) -> RunState {
@@ -62,45 +89,66 @@ pub fn player_input(gs: &mut State, ctx: &mut rltk::Rltk) -> RunState {
         Some(key) => match key {
             rltk::VirtualKeyCode::Left
             | rltk::VirtualKeyCode::Numpad4
-            | rltk::VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
+            | rltk::VirtualKeyCode::H => return try_move_player(-1, 0, &mut gs.ecs),
 
             rltk::VirtualKeyCode::Right
             | rltk::VirtualKeyCode::Numpad6
-            | rltk::VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
+            | rltk::VirtualKeyCode::L => return try_move_player(1, 0, &mut gs.ecs),
 
             rltk::VirtualKeyCode::Up | rltk::VirtualKeyCode::Numpad8 | rltk::VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
+                return try_move_player(0, -1, &mut gs.ecs)
             }
 
             rltk::VirtualKeyCode::Down
             | rltk::VirtualKeyCode::Numpad2
-            | rltk::VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
+            | rltk::VirtualKeyCode::J => return try_move_player(0, 1, &mut gs.ecs),
 
             rltk::VirtualKeyCode::Numpad9 | rltk::VirtualKeyCode::Y => {
-                try_move_player(1, -1, &mut gs.ecs)
+                return try_move_player(1, -1, &mut gs.ecs)
             }
 
             rltk::VirtualKeyCode::Numpad7 | rltk::VirtualKeyCode::U => {
-                try_move_player(-1, -1, &mut gs.ecs)
+                return try_move_player(-1, -1, &mut gs.ecs)
             }
 
             rltk::VirtualKeyCode::Numpad3 | rltk::VirtualKeyCode::N => {
-                try_move_player(1, 1, &mut gs.ecs)
+                return try_move_player(1, 1, &mut gs.ecs)
             }
 
             rltk::VirtualKeyCode::Numpad1 | rltk::VirtualKeyCode::B => {
-                try_move_player(-1, 1, &mut gs.ecs)
+                return try_move_player(-1, 1, &mut gs.ecs)
             }
 
             rltk::VirtualKeyCode::G => acquire_item(&mut gs.ecs),
 
+            rltk::VirtualKeyCode::C => return close_door(&mut gs.ecs),
+
+            rltk::VirtualKeyCode::S => return search(&mut gs.ecs),
+
+            rltk::VirtualKeyCode::Z => return toggle_lantern(&mut gs.ecs),
+
             rltk::VirtualKeyCode::I => return RunState::ShowInventory,
 
             rltk::VirtualKeyCode::D => return RunState::ShowDropItem,
 
+            rltk::VirtualKeyCode::T => return RunState::ShowThrowItem,
+
+            rltk::VirtualKeyCode::F => return fire(&mut gs.ecs),
+
             // Save and Quit
             rltk::VirtualKeyCode::Escape => return RunState::SaveGame,
 
+            // Save without quitting
+            rltk::VirtualKeyCode::F5 => {
+                save::save_game(&mut gs.ecs);
+                gs.ecs
+                    .fetch_mut::<GameLog>()
+                    .entries
+                    .push("Game saved.".to_string());
+
+                return RunState::AwaitingInput;
+            }
+
             rltk::VirtualKeyCode::Period => {
                 if try_next_level(&mut gs.ecs) {
                     return RunState::NextLevel;
@@ -154,6 +202,124 @@ fn acquire_item(ecs: &mut World) {
     }
 }
 
+/// Closes any open doors next to the player
+fn close_door(ecs: &mut World) -> RunState {
+    let player_pos = *ecs.fetch::<Point>();
+    let mut map = ecs.fetch_mut::<Map>();
+
+    let mut closed_door = false;
+
+    for y in player_pos.y - 1..=player_pos.y + 1 {
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if map.close_door(idx) {
+                closed_door = true;
+            }
+        }
+    }
+
+    if !closed_door {
+        let mut log = ecs.fetch_mut::<GameLog>();
+        log.entries
+            .push("There is no door to close here.".to_string());
+
+        return RunState::AwaitingInput;
+    }
+
+    // A closed door changes what everyone can see
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    for viewshed in (&mut viewsheds).join() {
+        viewshed.dirty = true;
+    }
+
+    RunState::PlayerTurn
+}
+
+/// Spends a turn looking for hidden things near the player
+fn search(ecs: &mut World) -> RunState {
+    let player_pos = *ecs.fetch::<Point>();
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+
+    log.entries
+        .push("You search your surroundings.".to_string());
+
+    let mut found: Vec<Entity> = Vec::new();
+    for (entity, _hidden, pos) in (&entities, &hidden, &positions).join() {
+        let distance =
+            rltk::DistanceAlg::Pythagoras.distance2d(player_pos, Point::new(pos.x, pos.y));
+
+        if distance <= SEARCH_RADIUS && rng.roll_dice(1, 3) == 1 {
+            found.push(entity);
+        }
+    }
+
+    for entity in found {
+        hidden.remove(entity);
+
+        if let Some(name) = names.get(entity) {
+            log.entries.push(format!("You find a {}.", name.name));
+        }
+    }
+
+    RunState::PlayerTurn
+}
+
+/// Shutters or unshutters the player's lantern. Without it the player sees less, but is harder to spot
+fn toggle_lantern(ecs: &mut World) -> RunState {
+    let player = *ecs.fetch::<Entity>();
+    let mut lights = ecs.write_storage::<LightSource>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+
+    let lantern = match lights.get_mut(player) {
+        Some(lantern) => lantern,
+        None => return RunState::AwaitingInput,
+    };
+
+    lantern.lit = !lantern.lit;
+
+    if lantern.lit {
+        log.entries.push("You unshutter your lantern.".to_string());
+    } else {
+        log.entries.push("You shutter your lantern.".to_string());
+    }
+
+    if let Some(viewshed) = viewsheds.get_mut(player) {
+        viewshed.dirty = true;
+    }
+
+    RunState::PlayerTurn
+}
+
+/// Takes aim with the player's ranged weapon at the nearest enemy in sight and range
+fn fire(ecs: &mut World) -> RunState {
+    let player = *ecs.fetch::<Entity>();
+
+    let message = match ranged_combat::equipped_ranged_weapon(ecs, player) {
+        None => "You have nothing to fire.",
+        Some(_) if ranged_combat::loaded_ammo(ecs, player).is_none() => {
+            "You have nothing to shoot with."
+        }
+        Some(_) => match ranged_combat::fire_targets(ecs, player).first() {
+            Some(target) => return RunState::FireTargeting { target: *target },
+            None => "There's nothing in range to shoot at.",
+        },
+    };
+
+    ecs.fetch_mut::<GameLog>().entries.push(message.to_string());
+
+    RunState::AwaitingInput
+}
+
 fn try_next_level(ecs: &mut World) -> bool {
     let map = ecs.fetch::<Map>();
 
@@ -1,4 +1,3 @@
-
 use rltk::RandomNumberGenerator;
 
 pub struct RandomEntry {
@@ -29,7 +28,13 @@ impl RandomTable {
         }
     }
 
+    /// Adds an entry to the table. Entries with no weight, such as depth-gated ones on levels too shallow for
+    /// them, are left out altogether
     pub fn add<S: ToString>(mut self, name: S, weight: i32) -> RandomTable {
+        if weight <= 0 {
+            return self;
+        }
+
         self.total_weight += weight;
         self.entries
             .push(RandomEntry::new(name.to_string(), weight));
@@ -0,0 +1,114 @@
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+use crate::components::SerializeOnSave;
+
+/// Invokes the given macro with every component that is registered with the world and persisted in saves.
+/// This is the one place a new component needs to be listed.
+///
+/// Any leading tokens are passed through ahead of the component types, e.g.
+/// `for_each_component!(my_macro!(ecs,))` expands to `my_macro!(ecs, Position, Renderable, ...)`
+#[macro_export]
+macro_rules! for_each_component {
+    ($mac:ident!($($args:tt)*)) => {
+        $mac!(
+            $($args)*
+            $crate::components::Position,
+            $crate::components::Renderable,
+            $crate::components::Player,
+            $crate::components::Viewshed,
+            $crate::components::LightSource,
+            $crate::components::Enemy,
+            $crate::components::OpensDoors,
+            $crate::components::Shopkeeper,
+            $crate::components::Purse,
+            $crate::components::GoldPile,
+            $crate::components::Name,
+            $crate::components::BlocksCell,
+            $crate::components::CombatStats,
+            $crate::components::Attributes,
+            $crate::components::Damage,
+            $crate::components::DesiresMelee,
+            $crate::components::Item,
+            $crate::components::Consumable,
+            $crate::components::Stackable,
+            $crate::components::Shatters,
+            $crate::components::Charges,
+            $crate::components::Recharges,
+            $crate::components::Identifies,
+            $crate::components::RemovesCurse,
+            $crate::components::Ranged,
+            $crate::components::InflictsDamage,
+            $crate::components::AreaOfEffect,
+            $crate::components::StatusEffect,
+            $crate::components::DetectsMonsters,
+            $crate::components::MagicMapper,
+            $crate::components::Teleports,
+            $crate::components::TeleportSpell,
+            $crate::components::ProvidesHealing,
+            $crate::components::InInventory,
+            $crate::components::Weight,
+            $crate::components::Value,
+            $crate::components::Equippable,
+            $crate::components::Equipped,
+            $crate::components::DamageDice,
+            $crate::components::MeleePowerBonus,
+            $crate::components::DefenseBonus,
+            $crate::components::RangedWeapon,
+            $crate::components::Ammunition,
+            $crate::components::Cursed,
+            $crate::components::DesiresAcquireItem,
+            $crate::components::DesiresUseItem,
+            $crate::components::DesiresDropItem,
+            $crate::components::DesiresEquipItem,
+            $crate::components::DesiresThrowItem,
+            $crate::components::DesiresFire,
+            $crate::components::DesiresTeleport,
+            $crate::components::Trap,
+            $crate::components::Hidden,
+            $crate::components::EntityMoved,
+            $crate::components::Alerted,
+            $crate::components::SerializationHelper
+        )
+    };
+}
+
+/// Registers every component, plus the save marker, with the world
+pub fn register_components(ecs: &mut World) {
+    macro_rules! register {
+        ($( $type:ty),*) => {
+            $(
+            ecs.register::<$type>();
+            )*
+        };
+    }
+
+    for_each_component!(register!());
+
+    ecs.register::<SimpleMarker<SerializeOnSave>>();
+}
+
+/// Creates a copy of the given entity with a clone of every component it has, marked to be saved
+pub fn clone_entity(ecs: &mut World, entity: Entity) -> Entity {
+    let copy = ecs
+        .create_entity()
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    macro_rules! clone_components {
+        ($( $type:ty),*) => {
+            $(
+            let component = ecs.read_storage::<$type>().get(entity).cloned();
+            if let Some(component) = component {
+                ecs.write_storage::<$type>()
+                    .insert(copy, component)
+                    .expect("unable to clone component");
+            }
+            )*
+        };
+    }
+
+    for_each_component!(clone_components!());
+
+    copy
+}
@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
+use std::collections::HashMap;
 use std::fs::{self, File};
+use std::io::Write;
 use std::path::Path;
 
 use specs::error::NoError;
@@ -7,13 +10,57 @@ use specs::saveload::{
     DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
 };
 
-use crate::{components::*, map};
 use crate::{
-    components::{SerializationHelper, SerializeOnSave},
+    components::{Player, Position, SerializationHelper, SerializeOnSave},
+    identification::Identification,
+    log::GameLog,
     map::Map,
 };
 
 const SAVE_FILE_PATH: &str = "./savegame.json";
+// Saves are written here first and then renamed over SAVE_FILE_PATH, so a crash mid-write can't corrupt the save
+const SAVE_TMP_FILE_PATH: &str = "./savegame.json.tmp";
+
+// Records the newest save generation seen for each run, used to detect restored copies of old saves
+const SAVE_LEDGER_PATH: &str = "./savegame.ledger";
+
+// Mixed into the save checksum so a hand-edited save can't simply be re-hashed. It ships in the source, so this
+// only stops casual edits; anyone who reads it can re-sign a save
+const SAVE_CHECKSUM_KEY: &[u8] = b"daddle-dat bodan";
+
+// The number of turns between autosaves
+pub const AUTOSAVE_INTERVAL: i32 = 50;
+
+/// Per-run bookkeeping carried in every save to detect save-scumming
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SaveMetadata {
+    // Randomly chosen when a new game starts
+    pub run_id: u64,
+    // Incremented each time the run is written to disk
+    pub generation: u64,
+    // Ironman runs delete their save on load
+    pub ironman: bool,
+    // Set once the run has been loaded from an edited or duplicated save, and never cleared
+    pub tampered: bool,
+}
+
+impl SaveMetadata {
+    /// Bookkeeping for a freshly started run, under a new run id and not yet saved
+    pub fn new_run(rng: &mut rltk::RandomNumberGenerator, ironman: bool) -> SaveMetadata {
+        SaveMetadata {
+            run_id: rng.next_u64(),
+            generation: 0,
+            ironman,
+            tampered: false,
+        }
+    }
+}
+
+/// Tracks the number of turns elapsed since the game was last saved
+#[derive(Default)]
+pub struct AutosaveTimer {
+    pub turns: i32,
+}
 
 macro_rules! serialize_individually {
     ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
@@ -39,11 +86,88 @@ macro_rules! deserialize_individually {
             &mut $data.2, // allocater
             &mut $de,
         )
-        .unwrap();
+        .map_err(|err| err.to_string())?;
         )*
     };
 }
 
+/// Keyed FNV-1a hash of the save body. Not cryptographic, and the key isn't secret, so it catches casual edits
+/// rather than offering real tamper protection
+fn checksum(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in SAVE_CHECKSUM_KEY
+        .iter()
+        .chain(data.iter())
+        .chain(SAVE_CHECKSUM_KEY.iter())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+fn read_ledger() -> HashMap<u64, u64> {
+    let mut ledger = HashMap::new();
+
+    if let Ok(contents) = fs::read_to_string(SAVE_LEDGER_PATH) {
+        for line in contents.lines() {
+            if let Some((run_id, generation)) = line.split_once(' ') {
+                if let (Ok(run_id), Ok(generation)) =
+                    (u64::from_str_radix(run_id, 16), generation.parse::<u64>())
+                {
+                    ledger.insert(run_id, generation);
+                }
+            }
+        }
+    }
+
+    ledger
+}
+
+/// Records the given generation as the newest one for the run
+fn write_ledger(run_id: u64, generation: u64) {
+    let mut ledger = read_ledger();
+    ledger.insert(run_id, generation);
+
+    let contents = ledger
+        .iter()
+        .map(|(run_id, generation)| format!("{:016x} {}\n", run_id, generation))
+        .collect::<String>();
+
+    fs::write(SAVE_LEDGER_PATH, contents).expect("unable to write save ledger");
+}
+
+/// Serializes every marked entity's components into a JSON body
+pub fn serialize_components(ecs: &World) -> Vec<u8> {
+    let mut serializer = serde_json::Serializer::new(Vec::new());
+
+    let data = (
+        ecs.entities(),
+        ecs.read_storage::<SimpleMarker<SerializeOnSave>>(),
+    );
+
+    crate::for_each_component!(serialize_individually!(ecs, serializer, data,));
+
+    serializer.into_inner()
+}
+
+/// Recreates the entities in a JSON body produced by `serialize_components`
+pub fn deserialize_components(ecs: &mut World, data: &str) -> Result<(), String> {
+    let mut deserialized = serde_json::Deserializer::from_str(data);
+
+    let mut d = (
+        &mut ecs.entities(),
+        &mut ecs.write_storage::<SimpleMarker<SerializeOnSave>>(),
+        &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeOnSave>>(),
+    );
+
+    crate::for_each_component!(deserialize_individually!(ecs, deserialized, d,));
+
+    Ok(())
+}
+
 pub fn has_save_file() -> bool {
     Path::new(SAVE_FILE_PATH).exists()
 }
@@ -54,140 +178,139 @@ pub fn save_game(_ecs: &mut World) {}
 #[cfg(not(target_arch = "wasm32"))]
 pub fn save_game(ecs: &mut World) {
     let map_cp = ecs.get_mut::<Map>().unwrap().clone();
+
+    let metadata = {
+        let mut metadata = ecs.write_resource::<SaveMetadata>();
+        metadata.generation += 1;
+        metadata.clone()
+    };
+
+    let identification = Identification::clone(&ecs.fetch::<Identification>());
+
     let save_helper = ecs
         .create_entity()
-        .with(SerializationHelper { map: map_cp })
+        .with(SerializationHelper {
+            map: map_cp,
+            metadata: metadata.clone(),
+            identification,
+        })
         .marked::<SimpleMarker<SerializeOnSave>>()
         .build();
 
     {
-        let writer = File::create(SAVE_FILE_PATH).unwrap();
-        let mut serializer = serde_json::Serializer::new(writer);
-
-        let data = (
-            ecs.entities(),
-            ecs.read_storage::<SimpleMarker<SerializeOnSave>>(),
-        );
-
-        serialize_individually!(
-            ecs,
-            serializer,
-            data,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Enemy,
-            Name,
-            BlocksCell,
-            CombatStats,
-            Damage,
-            DesiresMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            StatusEffect,
-            ProvidesHealing,
-            InInventory,
-            DesiresAcquireItem,
-            DesiresUseItem,
-            DesiresDropItem,
-            SerializationHelper
-        );
+        let body = serialize_components(ecs);
+
+        // The checksum goes on the first line, ahead of the save body it covers
+        let mut writer = File::create(SAVE_TMP_FILE_PATH).expect("unable to create save file");
+        writeln!(writer, "{:016x}", checksum(&body)).expect("unable to write save file");
+        writer.write_all(&body).expect("unable to write save file");
+
+        // Make sure everything has hit the disk before swapping the new save in
+        writer.sync_all().expect("unable to flush save file");
     }
 
+    fs::rename(SAVE_TMP_FILE_PATH, SAVE_FILE_PATH).expect("unable to replace save file");
+    write_ledger(metadata.run_id, metadata.generation);
+
     ecs.delete_entity(save_helper)
         .expect("crash on save cleanup");
-    // let d = serde_json::to_string(&*ecs.fetch::<Map>()).unwrap();
-    // println!("{}", d);
+
+    ecs.write_resource::<AutosaveTimer>().turns = 0;
 }
 
-pub fn load_game(ecs: &mut World) {
-    {
-        let mut to_delete = Vec::new();
+/// Replaces the world with the one in the save file. If the save can't be read, the world is left empty and
+/// the reason is returned
+pub fn load_game(ecs: &mut World) -> Result<(), String> {
+    let contents = fs::read_to_string(SAVE_FILE_PATH).map_err(|err| err.to_string())?;
 
-        // Add every entity in the game into a delete list (we'll iterate deletes separately to avoid invalidating the iterator)
-        for entity in ecs.entities().join() {
-            to_delete.push(entity);
-        }
+    ecs.delete_all();
 
-        // Delete every entity
-        for del in to_delete.iter() {
-            ecs.delete_entity(*del).expect("delete entity failed");
-        }
+    let loaded = restore_world(ecs, &contents);
+    if loaded.is_err() {
+        ecs.delete_all();
     }
 
-    // Open the save file and deserialize it
-    let data = fs::read_to_string(SAVE_FILE_PATH).unwrap();
-    let mut deserialized = serde_json::Deserializer::from_str(&data);
+    loaded
+}
+
+fn restore_world(ecs: &mut World, contents: &str) -> Result<(), String> {
+    // The checksum goes on the first line, ahead of the save body it covers. A save without one has had it
+    // stripped, and counts as edited
+    let (checksum_matches, data) = match contents.split_once('\n') {
+        Some((header, data)) => match u64::from_str_radix(header, 16) {
+            Ok(sum) => (sum == checksum(data.as_bytes()), data),
+            Err(_) => (false, contents),
+        },
+        None => (false, contents),
+    };
+
+    deserialize_components(ecs, data)?;
+
+    let helper = {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+
+        (&entities, &helpers)
+            .join()
+            .map(|(entity, helper)| (entity, helper.clone()))
+            .next()
+    };
+    let (helper_entity, helper) = helper.ok_or("save has no game state")?;
 
     {
-        // Deserialize every entity
-        let mut d = (
-            &mut ecs.entities(),
-            &mut ecs.write_storage::<SimpleMarker<SerializeOnSave>>(),
-            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeOnSave>>(),
-        );
-
-        deserialize_individually!(
-            ecs,
-            deserialized,
-            d,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Enemy,
-            Name,
-            BlocksCell,
-            CombatStats,
-            Damage,
-            DesiresMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            StatusEffect,
-            ProvidesHealing,
-            InInventory,
-            DesiresAcquireItem,
-            DesiresUseItem,
-            DesiresDropItem,
-            SerializationHelper
-        );
+        let mut map = ecs.write_resource::<Map>();
+        *map = helper.map;
+        map.cell_content = vec![Vec::new(); (map.width * map.height) as usize];
+        map.light = vec![rltk::RGB::from_f32(0., 0., 0.); (map.width * map.height) as usize];
     }
 
-    let mut to_delete: Option<Entity> = None;
-    {
+    *ecs.write_resource::<Identification>() = helper.identification;
+    *ecs.write_resource::<SaveMetadata>() = helper.metadata;
+
+    let player = {
         let entities = ecs.entities();
-        let helpers = ecs.read_storage::<SerializationHelper>();
         let players = ecs.read_storage::<Player>();
         let positions = ecs.read_storage::<Position>();
 
-        // Iterate all entities and build the map
-        for (entity, helper) in (&entities, &helpers).join() {
-            let mut map = ecs.write_resource::<Map>();
-            *map = helper.map.clone();
-            map.cell_content = vec![Vec::new(); map::MAP_COUNT];
-            to_delete = Some(entity);
-        }
-
-        // Set the player and player position
-        for (entity, _player, pos) in (&entities, &players, &positions).join() {
-            let mut player_pos = ecs.write_resource::<rltk::Point>();
-            *player_pos = rltk::Point::new(pos.x, pos.y);
+        (&entities, &players, &positions)
+            .join()
+            .map(|(entity, _player, pos)| (entity, rltk::Point::new(pos.x, pos.y)))
+            .next()
+    };
+    let (player, player_pos) = player.ok_or("save has no player")?;
 
-            let mut player = ecs.write_resource::<Entity>();
-            *player = entity;
-        }
-    }
+    *ecs.write_resource::<rltk::Point>() = player_pos;
+    *ecs.write_resource::<Entity>() = player;
 
     // Delete the helper so we don't have duplicates when next saving the game
-    ecs.delete_entity(to_delete.unwrap())
+    ecs.delete_entity(helper_entity)
         .expect("unable to delete helper");
+
+    verify_integrity(ecs, checksum_matches);
+
+    Ok(())
+}
+
+/// Flags the run if the save was edited, or is a copy of a save that has since been superseded or consumed
+fn verify_integrity(ecs: &mut World, checksum_matches: bool) {
+    let mut metadata = ecs.write_resource::<SaveMetadata>();
+
+    let newest_generation = read_ledger().get(&metadata.run_id).copied().unwrap_or(0);
+    let duplicated = metadata.generation < newest_generation;
+
+    if (!checksum_matches || duplicated) && !metadata.tampered {
+        metadata.tampered = true;
+
+        let mut log = ecs.fetch_mut::<GameLog>();
+        log.entries
+            .push("This save has been tampered with. The run will be flagged.".to_string());
+    }
+
+    if metadata.ironman {
+        // Consume this generation, so restoring a copy of the save file is detected
+        write_ledger(metadata.run_id, metadata.generation + 1);
+        delete_save();
+    }
 }
 
 pub fn delete_save() {
@@ -0,0 +1,134 @@
+use specs::prelude::*;
+
+use crate::{
+    components::{Attributes, Equipped, InInventory, Name, Purse, Stackable, Value, Weight},
+    identification::Identification,
+    log::GameLog,
+    systems::inventory,
+};
+
+// Shopkeepers only pay this fraction of what they'd charge
+const SELL_DIVISOR: i32 = 2;
+
+/// What a shopkeeper charges for one of an item worth the given value
+pub fn buy_price(value: &Value) -> i32 {
+    value.gold
+}
+
+/// What a shopkeeper pays for one of an item worth the given value
+pub fn sell_price(value: &Value) -> i32 {
+    i32::max(1, value.gold / SELL_DIVISOR)
+}
+
+/// Whether the seller can offer the item to a shopkeeper: it has to be worth something and not in use
+pub fn can_sell(ecs: &World, item: Entity) -> bool {
+    ecs.read_storage::<Equipped>().get(item).is_none()
+        && ecs.read_storage::<Value>().get(item).is_some()
+}
+
+/// Moves one of the item from the shopkeeper's stock to the buyer for its price, as long as the buyer can pay
+/// for it and carry it
+pub fn buy(ecs: &mut World, buyer: Entity, shopkeeper: Entity, item: Entity) {
+    let (item_name, price) = {
+        let names = ecs.read_storage::<Name>();
+        let name = names.get(item).unwrap();
+
+        (
+            ecs.fetch::<Identification>().name_of(name),
+            ecs.read_storage::<Value>().get(item).map_or(0, buy_price),
+        )
+    };
+
+    let gold = ecs
+        .read_storage::<Purse>()
+        .get(buyer)
+        .map_or(0, |purse| purse.gold);
+    if gold < price {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("You can't afford the {}.", item_name));
+        return;
+    }
+
+    if !can_carry(ecs, buyer, item) {
+        ecs.fetch_mut::<GameLog>().entries.push(format!(
+            "You can't carry the {}, your pack is too heavy",
+            item_name
+        ));
+        return;
+    }
+
+    let bought = inventory::take_one_from_stack(ecs, item);
+    inventory::add_to_inventory(ecs, bought, buyer);
+    pay(ecs, buyer, shopkeeper, price);
+
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You buy the {} for {} gold.", item_name, price));
+}
+
+/// Moves one of the item from the seller to the shopkeeper's stock, as long as the shopkeeper can pay for it
+pub fn sell(ecs: &mut World, seller: Entity, shopkeeper: Entity, item: Entity) {
+    let (item_name, price) = {
+        let names = ecs.read_storage::<Name>();
+        let name = names.get(item).unwrap();
+
+        (
+            ecs.fetch::<Identification>().name_of(name),
+            ecs.read_storage::<Value>().get(item).map_or(0, sell_price),
+        )
+    };
+
+    let gold = ecs
+        .read_storage::<Purse>()
+        .get(shopkeeper)
+        .map_or(0, |purse| purse.gold);
+    if gold < price {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("The shopkeeper can't afford your {}.", item_name));
+        return;
+    }
+
+    let sold = inventory::take_one_from_stack(ecs, item);
+    inventory::add_to_inventory(ecs, sold, shopkeeper);
+    pay(ecs, shopkeeper, seller, price);
+
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You sell the {} for {} gold.", item_name, price));
+}
+
+/// Whether the carrier has room for one more of the item
+fn can_carry(ecs: &World, carrier: Entity, item: Entity) -> bool {
+    let entities = ecs.entities();
+    let in_inventory = ecs.read_storage::<InInventory>();
+    let weights = ecs.read_storage::<Weight>();
+    let stacks = ecs.read_storage::<Stackable>();
+
+    let attributes = match ecs.read_storage::<Attributes>().get(carrier) {
+        Some(attributes) => attributes.clone(),
+        None => return true,
+    };
+
+    let carried: i32 = (&entities, &in_inventory, &weights)
+        .join()
+        .filter(|(_, carried, _)| carried.owner == carrier)
+        .map(|(item, _, weight)| weight.of_stack(stacks.get(item)))
+        .sum();
+    let adding = weights.get(item).map_or(0, |weight| weight.weight);
+
+    carried + adding <= attributes.carrying_capacity()
+}
+
+fn pay(ecs: &mut World, from: Entity, to: Entity, amount: i32) {
+    let mut purses = ecs.write_storage::<Purse>();
+
+    if let Some(purse) = purses.get_mut(from) {
+        purse.gold -= amount;
+    }
+
+    if let Some(purse) = purses.get_mut(to) {
+        purse.gold += amount;
+    }
+}
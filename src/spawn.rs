@@ -8,17 +8,30 @@ use specs::{
 
 use crate::{
     components::{
-        AreaOfEffect, BlocksCell, CombatStats, Consumable, Enemy, InflictsDamage, Item, Name,
-        Player, Position, ProvidesHealing, Ranged, Renderable, SerializeOnSave, StatusEffect,
-        StatusEffectType, Viewshed,
+        AmmoType, Ammunition, AreaOfEffect, Attributes, BlocksCell, Charges, CombatStats,
+        Consumable, Cursed, DamageDice, DefenseBonus, DetectsMonsters, Enemy, EquipmentSlot,
+        Equippable, GoldPile, Hidden, Identifies, InflictsDamage, Item, LightSource, MagicMapper,
+        MeleePowerBonus, Name, OpensDoors, Player, Position, ProvidesHealing, Purse, Ranged,
+        RangedWeapon, Recharges, RemovesCurse, Renderable, SerializeOnSave, Shatters, Shopkeeper,
+        Stackable, StatusEffect, StatusEffectType, TeleportSpell, Teleports, Trap, TrapType, Value,
+        Viewshed, Weight,
     },
     geometry::Rect,
-    map::MAP_WIDTH,
+    map::{CellType, Map},
     random_table::RandomTable,
+    systems::inventory,
 };
 
 const MAX_ENEMIES: i32 = 4;
-const MAX_ITEMS: i32 = 2;
+
+// Each region has a 1 in this chance of holding a light
+const LIGHT_CHANCE: i32 = 3;
+
+// Each piece of equipment has a 1 in this chance of being cursed
+const CURSE_CHANCE: i32 = 5;
+
+// How many items a shopkeeper has for sale
+const SHOP_STOCK: i32 = 8;
 
 pub fn player(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
@@ -39,69 +52,204 @@ pub fn player(ecs: &mut World, x: i32, y: i32) -> Entity {
             defense: 2,
             power: 5,
         })
+        .with(Attributes {
+            strength: 10,
+            dexterity: 12,
+        })
+        .with(Purse { gold: 0 })
         .with(Viewshed {
             visible_cells: Vec::new(),
-            range: 8,
+            range: 12,
             dirty: true,
         })
+        .with(LightSource {
+            color: rltk::RGB::from_f32(1., 0.9, 0.6),
+            radius: 5,
+            lit: true,
+        })
         .marked::<SimpleMarker<SerializeOnSave>>()
         .build()
 }
 
 pub fn room(ecs: &mut World, room: &Rect, map_depth: i32) {
+    let mut area: Vec<usize> = Vec::new();
+
+    {
+        let map = ecs.fetch::<Map>();
+
+        for y in room.y1 + 1..=room.y2 {
+            for x in room.x1 + 1..=room.x2 {
+                let idx = map.xy_idx(x, y);
+                if map.cells[idx] == CellType::Floor {
+                    area.push(idx);
+                }
+            }
+        }
+    }
+
+    region(ecs, &area, map_depth);
+}
+
+/// Spawns a random selection of entities on the given cells
+pub fn region(ecs: &mut World, area: &[usize], map_depth: i32) {
     let spawn_table = room_table(map_depth);
+    let map_width = ecs.fetch::<Map>().width;
     let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    let mut available = area.to_vec();
 
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_spawns = rng.roll_dice(1, MAX_ENEMIES + 3) + (map_depth - 1) - 3;
+
+        // Torches are kept lit near the surface; further down only fungus glows
+        if !available.is_empty() && rng.roll_dice(1, LIGHT_CHANCE) == 1 {
+            let pick = (rng.roll_dice(1, available.len() as i32) - 1) as usize;
+            let idx = available.remove(pick);
+            let light = if rng.roll_dice(1, 10) <= map_depth {
+                "Glowing Fungus"
+            } else {
+                "Torch"
+            };
+
+            spawn_points.insert(idx, light.to_string());
+        }
+
+        let num_spawns = i32::min(
+            available.len() as i32,
+            rng.roll_dice(1, MAX_ENEMIES + 3) + (map_depth - 1) - 3,
+        );
 
         for _i in 0..num_spawns {
-            let mut added = false;
-            let mut tries = 0;
-
-            // Keep picking random cells until we find an empty one
-            while !added && tries < 20 {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAP_WIDTH as usize) + x;
-
-                if !spawn_points.contains_key(&idx) {
-                    spawn_points.insert(idx, spawn_table.roll(&mut rng));
-                    added = true;
-                } else {
-                    tries += 1;
-                }
-            }
+            // Take a random cell out of the pool, so no two spawns share one
+            let pick = (rng.roll_dice(1, available.len() as i32) - 1) as usize;
+            let idx = available.remove(pick);
+
+            spawn_points.insert(idx, spawn_table.roll(&mut rng));
         }
     }
 
     for spawn in spawn_points.iter() {
-        let x = *spawn.0 as i32 % MAP_WIDTH;
-        let y = *spawn.0 as i32 / MAP_WIDTH;
-
-        match spawn.1.as_ref() {
-            "Goblin" => spawn_goblin(ecs, x, y),
-            "Orc" => spawn_orc(ecs, x, y),
-            "Health Potion" => spawn_health_potion(ecs, x, y),
-            "Fireball Scroll" => spawn_fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => spawn_confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => spawn_missile_scroll(ecs, x, y),
-            _ => {}
+        let x = *spawn.0 as i32 % map_width;
+        let y = *spawn.0 as i32 / map_width;
+
+        spawn_entity(ecs, x, y, spawn.1);
+    }
+}
+
+/// Spawns the named entity at x, y; unknown names spawn nothing
+pub fn spawn_entity(ecs: &mut World, x: i32, y: i32, name: &str) {
+    match name {
+        "Goblin" => spawn_goblin(ecs, x, y),
+        "Orc" => spawn_orc(ecs, x, y),
+        "Rat" => spawn_rat(ecs, x, y),
+        "Imp" => spawn_imp(ecs, x, y),
+        "Shopkeeper" => spawn_shopkeeper(ecs, x, y),
+        "Gold" => spawn_gold(ecs, x, y),
+        "Health Potion" => spawn_health_potion(ecs, x, y),
+        "Torch" => spawn_light(
+            ecs,
+            x,
+            y,
+            "Torch",
+            'î',
+            rltk::RGB::from_f32(1., 0.6, 0.2),
+            6,
+        ),
+        "Glowing Fungus" => spawn_light(
+            ecs,
+            x,
+            y,
+            "Glowing Fungus",
+            '♣',
+            rltk::RGB::from_f32(0.3, 0.9, 0.6),
+            3,
+        ),
+        "Fireball Scroll" => spawn_fireball_scroll(ecs, x, y),
+        "Confusion Scroll" => spawn_confusion_scroll(ecs, x, y),
+        "Magic Missile Scroll" => spawn_missile_scroll(ecs, x, y),
+        "Magic Mapping Scroll" => spawn_magic_mapping_scroll(ecs, x, y),
+        "Teleport Scroll" => spawn_teleport_scroll(ecs, x, y),
+        "Recharge Scroll" => spawn_recharge_scroll(ecs, x, y),
+        "Identify Scroll" => spawn_identify_scroll(ecs, x, y),
+        "Remove Curse Scroll" => spawn_remove_curse_scroll(ecs, x, y),
+        "Dagger" => spawn_melee_weapon(ecs, x, y, "Dagger", (1, 4), 1, 2, 15),
+        "Longsword" => spawn_melee_weapon(ecs, x, y, "Longsword", (1, 8), 2, 4, 50),
+        "Shield" => {
+            spawn_equipment(ecs, x, y, "Shield", '(', EquipmentSlot::Shield, 1, 4, 20);
+        }
+        "Tower Shield" => {
+            spawn_equipment(
+                ecs,
+                x,
+                y,
+                "Tower Shield",
+                '(',
+                EquipmentSlot::Shield,
+                3,
+                8,
+                60,
+            );
         }
+        "Shortbow" => spawn_ranged_weapon(ecs, x, y, "Shortbow", AmmoType::Arrow, 8, (1, 6), 3, 40),
+        "Crossbow" => spawn_ranged_weapon(ecs, x, y, "Crossbow", AmmoType::Bolt, 6, (1, 10), 6, 70),
+        "Arrow" => spawn_ammunition(ecs, x, y, "Arrow", AmmoType::Arrow, 2),
+        "Bolt" => spawn_ammunition(ecs, x, y, "Bolt", AmmoType::Bolt, 3),
+        "Magic Missile Wand" => spawn_missile_wand(ecs, x, y),
+        "Sleep Wand" => spawn_sleep_wand(ecs, x, y),
+        "Blink Scroll" => spawn_blink_scroll(ecs, x, y),
+        "Detect Monsters Potion" => spawn_detect_monsters_potion(ecs, x, y),
+        "Spike Trap" => spawn_trap(ecs, x, y, TrapType::Spike, "Spike Trap", rltk::GREY),
+        "Poison Dart Trap" => spawn_trap(
+            ecs,
+            x,
+            y,
+            TrapType::PoisonDart,
+            "Poison Dart Trap",
+            rltk::GREEN,
+        ),
+        "Teleport Trap" => spawn_trap(
+            ecs,
+            x,
+            y,
+            TrapType::Teleport,
+            "Teleport Trap",
+            rltk::MAGENTA,
+        ),
+        "Alarm Trap" => spawn_trap(ecs, x, y, TrapType::Alarm, "Alarm Trap", rltk::YELLOW),
+        "Trapdoor" => spawn_trap(ecs, x, y, TrapType::Trapdoor, "Trapdoor", rltk::CHOCOLATE),
+        _ => {}
     }
 }
 
 fn spawn_orc(ecs: &mut World, x: i32, y: i32) {
-    spawn_enemy(ecs, x, y, rltk::to_cp437('o'), "Orc");
+    spawn_enemy(ecs, x, y, rltk::to_cp437('o'), "Orc", true);
 }
 
 fn spawn_goblin(ecs: &mut World, x: i32, y: i32) {
-    spawn_enemy(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+    spawn_enemy(ecs, x, y, rltk::to_cp437('g'), "Goblin", true);
 }
 
-fn spawn_enemy<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
-    ecs.create_entity()
+fn spawn_rat(ecs: &mut World, x: i32, y: i32) {
+    spawn_enemy(ecs, x, y, rltk::to_cp437('r'), "Rat", false);
+}
+
+fn spawn_imp(ecs: &mut World, x: i32, y: i32) {
+    let imp = spawn_enemy(ecs, x, y, rltk::to_cp437('i'), "Imp", true);
+
+    ecs.write_storage::<TeleportSpell>()
+        .insert(imp, TeleportSpell { range: 6 })
+        .expect("unable to insert teleport spell");
+}
+
+fn spawn_enemy<S: ToString>(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    glyph: rltk::FontCharType,
+    name: S,
+    opens_doors: bool,
+) -> Entity {
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph,
@@ -124,26 +272,116 @@ fn spawn_enemy<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCh
             defense: 1,
             power: 4,
         })
+        .with(BlocksCell {});
+
+    // Animals can't work door handles
+    if opens_doors {
+        builder = builder.with(OpensDoors {});
+    }
+
+    builder.marked::<SimpleMarker<SerializeOnSave>>().build()
+}
+
+/// Spawns a shopkeeper with a purse to buy with and a stock of items drawn from the shop table
+fn spawn_shopkeeper(ecs: &mut World, x: i32, y: i32) {
+    let depth = ecs.fetch::<Map>().depth;
+
+    let shopkeeper = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: rltk::RGB::named(rltk::GOLD),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Name {
+            name: "Shopkeeper".to_string(),
+        })
         .with(BlocksCell {})
+        .with(Shopkeeper {})
+        .with(Purse {
+            gold: 100 + depth * 50,
+        })
         .marked::<SimpleMarker<SerializeOnSave>>()
         .build();
-}
 
-fn spawn_random_item(ecs: &mut World, x: i32, y: i32) {
-    let roll: i32;
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+    let stock_table = shop_table(depth);
+
+    for _ in 0..SHOP_STOCK {
+        let roll = stock_table.roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+        spawn_entity(ecs, x, y, &roll);
     }
 
-    match roll {
-        1 => spawn_health_potion(ecs, x, y),
-        2 => spawn_fireball_scroll(ecs, x, y),
-        3 => spawn_confusion_scroll(ecs, x, y),
-        _ => spawn_missile_scroll(ecs, x, y),
+    // The stock is spawned underfoot, then taken into the shopkeeper's inventory
+    let stock: Vec<Entity> = {
+        let entities = ecs.entities();
+        let items = ecs.read_storage::<Item>();
+        let positions = ecs.read_storage::<Position>();
+
+        (&entities, &items, &positions)
+            .join()
+            .filter(|(_, _, pos)| pos.x == x && pos.y == y)
+            .map(|(entity, _, _)| entity)
+            .collect()
+    };
+
+    for item in stock {
+        inventory::add_to_inventory(ecs, item, shopkeeper);
     }
 }
 
+fn spawn_gold(ecs: &mut World, x: i32, y: i32) {
+    let depth = ecs.fetch::<Map>().depth;
+    let amount = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(depth + 1, 10);
+
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('$'),
+            fg: rltk::RGB::named(rltk::GOLD),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Gold".to_string(),
+        })
+        .with(GoldPile { amount })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_light(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    glyph: char,
+    color: rltk::RGB,
+    radius: i32,
+) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(glyph),
+            fg: color,
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(LightSource {
+            color,
+            radius,
+            lit: true,
+        })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
 fn spawn_health_potion(ecs: &mut World, x: i32, y: i32) {
     ecs.create_entity()
         .with(Position { x, y })
@@ -157,7 +395,11 @@ fn spawn_health_potion(ecs: &mut World, x: i32, y: i32) {
             name: "Health Potion".to_string(),
         })
         .with(Item {})
+        .with(Weight { weight: 2 })
+        .with(Value { gold: 20 })
+        .with(Shatters {})
         .with(Consumable {})
+        .with(Stackable { quantity: 1 })
         .with(ProvidesHealing { heal_amount: 8 })
         .marked::<SimpleMarker<SerializeOnSave>>()
         .build();
@@ -173,10 +415,13 @@ fn spawn_missile_scroll(ecs: &mut World, x: i32, y: i32) {
             render_order: 2,
         })
         .with(Name {
-            name: "Magick Missile Scroll".to_string(),
+            name: "Magic Missile Scroll".to_string(),
         })
         .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 25 })
         .with(Consumable {})
+        .with(Stackable { quantity: 1 })
         .with(Ranged { range: 6 })
         .with(InflictsDamage { damage: 8 })
         .marked::<SimpleMarker<SerializeOnSave>>()
@@ -196,7 +441,10 @@ fn spawn_fireball_scroll(ecs: &mut World, x: i32, y: i32) {
             name: "Fireball Scroll".to_string(),
         })
         .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 40 })
         .with(Consumable {})
+        .with(Stackable { quantity: 1 })
         .with(Ranged { range: 6 })
         .with(InflictsDamage { damage: 20 })
         .with(AreaOfEffect { radius: 3 })
@@ -217,7 +465,10 @@ fn spawn_confusion_scroll(ecs: &mut World, x: i32, y: i32) {
             name: "Confusion Scroll".to_string(),
         })
         .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 30 })
         .with(Consumable {})
+        .with(Stackable { quantity: 1 })
         .with(Ranged { range: 6 })
         .with(StatusEffect {
             effect: StatusEffectType::Confusion,
@@ -228,12 +479,444 @@ fn spawn_confusion_scroll(ecs: &mut World, x: i32, y: i32) {
         .build();
 }
 
+fn spawn_magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::CYAN3),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Magic Mapping Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 40 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(MagicMapper {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_recharge_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::GOLD),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Recharge Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 60 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(Recharges {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_identify_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::WHITE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Identify Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 20 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(Identifies {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_remove_curse_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::GOLD),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Remove Curse Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 50 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(RemovesCurse {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+/// Spawns a weapon or shield granting the given bonus to power or defense, depending on its slot. Cursed
+/// equipment takes the bonus away instead
+#[allow(clippy::too_many_arguments)]
+fn spawn_equipment(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    glyph: char,
+    slot: EquipmentSlot,
+    bonus: i32,
+    weight: i32,
+    value: i32,
+) -> Entity {
+    let cursed = roll_curse(ecs);
+    let bonus = if cursed { -bonus } else { bonus };
+
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(glyph),
+            fg: rltk::RGB::named(rltk::CYAN),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight })
+        .with(Value { gold: value })
+        .with(Equippable { slot });
+
+    builder = match slot {
+        EquipmentSlot::Melee => builder.with(MeleePowerBonus { power: bonus }),
+        EquipmentSlot::Shield => builder.with(DefenseBonus { defense: bonus }),
+        EquipmentSlot::Ranged => builder,
+    };
+
+    if cursed {
+        builder = builder.with(Cursed { known: false });
+    }
+
+    builder.marked::<SimpleMarker<SerializeOnSave>>().build()
+}
+
+/// Spawns a weapon rolling the given number and type of damage dice, with a bonus to hit and damage
+#[allow(clippy::too_many_arguments)]
+fn spawn_melee_weapon(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    (n_dice, die_type): (i32, i32),
+    bonus: i32,
+    weight: i32,
+    value: i32,
+) {
+    let weapon = spawn_equipment(
+        ecs,
+        x,
+        y,
+        name,
+        '/',
+        EquipmentSlot::Melee,
+        bonus,
+        weight,
+        value,
+    );
+
+    ecs.write_storage::<DamageDice>()
+        .insert(weapon, DamageDice { n_dice, die_type })
+        .expect("unable to insert weapon damage dice");
+}
+
+/// Spawns a bow or crossbow shooting the given type of ammunition. A cursed one shoots no worse, but can't be
+/// put down
+#[allow(clippy::too_many_arguments)]
+fn spawn_ranged_weapon(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    ammo: AmmoType,
+    range: i32,
+    (n_dice, die_type): (i32, i32),
+    weight: i32,
+    value: i32,
+) {
+    let cursed = roll_curse(ecs);
+
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('}'),
+            fg: rltk::RGB::named(rltk::CYAN),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight })
+        .with(Value { gold: value })
+        .with(Equippable {
+            slot: EquipmentSlot::Ranged,
+        })
+        .with(RangedWeapon {
+            range,
+            damage: DamageDice { n_dice, die_type },
+            ammo,
+        });
+
+    if cursed {
+        builder = builder.with(Cursed { known: false });
+    }
+
+    builder.marked::<SimpleMarker<SerializeOnSave>>().build();
+}
+
+/// Spawns a small bundle of arrows or bolts. They're light enough not to weigh anything
+fn spawn_ammunition(ecs: &mut World, x: i32, y: i32, name: &str, ammo: AmmoType, value: i32) {
+    let quantity = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(2, 4);
+
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('↑'),
+            fg: rltk::RGB::named(rltk::BURLYWOOD),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Value { gold: value })
+        .with(Stackable { quantity })
+        .with(Ammunition { ammo })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+/// Whether a newly spawned piece of equipment comes cursed
+fn roll_curse(ecs: &mut World) -> bool {
+    ecs.write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, CURSE_CHANCE)
+        == 1
+}
+
+fn spawn_missile_wand(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('/'),
+            fg: rltk::RGB::named(rltk::CYAN),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Magic Missile Wand".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 80 })
+        .with(Charges {
+            current: 5,
+            max: 5,
+            destroyed_when_empty: false,
+        })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 8 })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_sleep_wand(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('/'),
+            fg: rltk::RGB::named(rltk::LIGHT_BLUE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Sleep Wand".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 70 })
+        .with(Charges {
+            current: 3,
+            max: 3,
+            destroyed_when_empty: true,
+        })
+        .with(Ranged { range: 6 })
+        .with(StatusEffect {
+            effect: StatusEffectType::Sleep,
+            turns: 8,
+            print_as: "lulling".to_string(),
+        })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_teleport_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::MEDIUM_PURPLE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Teleport Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 30 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(Teleports {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_blink_scroll(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: rltk::RGB::named(rltk::VIOLET),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Blink Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 35 })
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(Ranged { range: 5 })
+        .with(Teleports {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_detect_monsters_potion(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('¡'),
+            fg: rltk::RGB::named(rltk::ORANGE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Detect Monsters Potion".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { weight: 2 })
+        .with(Value { gold: 30 })
+        .with(Shatters {})
+        .with(Consumable {})
+        .with(Stackable { quantity: 1 })
+        .with(DetectsMonsters { turns: 20 })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
+fn spawn_trap(ecs: &mut World, x: i32, y: i32, kind: TrapType, name: &str, color: (u8, u8, u8)) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: rltk::RGB::named(color),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Trap { kind })
+        .with(Hidden {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+}
+
 fn room_table(map_depth: i32) -> RandomTable {
     RandomTable::new()
         .add("Goblin", 10)
         .add("Org", 1 + map_depth)
+        .add("Rat", 6)
+        .add("Imp", map_depth - 1)
         .add("Health Potion", 7)
         .add("Fireball scroll", 2 + map_depth)
         .add("Confusion scroll", 2 + map_depth)
         .add("Magic Missile Scroll", 4)
+        .add("Magic Mapping Scroll", 2)
+        .add("Teleport Scroll", 2)
+        .add("Blink Scroll", 2)
+        .add("Recharge Scroll", 1)
+        .add("Identify Scroll", 3)
+        .add("Remove Curse Scroll", 2)
+        .add("Dagger", 3)
+        .add("Longsword", map_depth - 1)
+        .add("Shield", 3)
+        .add("Tower Shield", map_depth - 1)
+        .add("Shortbow", 2)
+        .add("Crossbow", map_depth - 1)
+        .add("Arrow", 3)
+        .add("Bolt", map_depth - 1)
+        .add("Magic Missile Wand", 1 + map_depth / 2)
+        .add("Sleep Wand", 1 + map_depth / 2)
+        .add("Detect Monsters Potion", 2)
+        .add("Gold", 6)
+        .add("Spike Trap", 3)
+        .add("Poison Dart Trap", map_depth)
+        .add("Teleport Trap", 1)
+        .add("Alarm Trap", 1)
+        .add("Trapdoor", 1)
+}
+
+/// What a shopkeeper at the given depth stocks
+fn shop_table(map_depth: i32) -> RandomTable {
+    RandomTable::new()
+        .add("Health Potion", 6)
+        .add("Detect Monsters Potion", 2)
+        .add("Identify Scroll", 4)
+        .add("Remove Curse Scroll", 2)
+        .add("Magic Missile Scroll", 3)
+        .add("Magic Mapping Scroll", 2)
+        .add("Teleport Scroll", 2)
+        .add("Recharge Scroll", 1)
+        .add("Magic Missile Wand", 1)
+        .add("Dagger", 2)
+        .add("Shield", 2)
+        .add("Longsword", map_depth - 1)
+        .add("Tower Shield", map_depth - 1)
+        .add("Shortbow", 1)
+        .add("Crossbow", map_depth - 2)
+        .add("Arrow", 3)
+        .add("Bolt", map_depth - 2)
 }
@@ -3,27 +3,42 @@ use rltk::{self};
 use specs::prelude::*;
 use specs::World;
 
+use crate::camera;
 use crate::components::DesiresUseItem;
 use crate::components::InInventory;
 use crate::components::Player;
 use crate::components::Position;
 use crate::components::Ranged;
-use crate::components::Renderable;
-use crate::components::{DesiresDropItem, Viewshed};
+use crate::components::{
+    DesiresDropItem, DesiresEquipItem, DesiresFire, DesiresThrowItem, Equippable, Viewshed,
+};
+use crate::identification::Identification;
 use crate::log::GameLog;
-use crate::map::draw_map;
-use crate::map::Map;
+use crate::map::{CellType, Map};
+use crate::map_builders;
 use crate::player::player_input;
 use crate::save;
-use crate::spawn::{self};
+use crate::shop;
+use crate::spawn;
 use crate::systems::damage;
-use crate::systems::inventory::ItemAcquisitionSystem;
 use crate::systems::inventory::ItemDropSystem;
+use crate::systems::inventory::ItemEquipSystem;
 use crate::systems::inventory::ItemUseSystem;
+use crate::systems::inventory::{self, ItemAcquisitionSystem};
+use crate::systems::ranged_combat;
+use crate::systems::throw;
 use crate::systems::DamageSystem;
 use crate::systems::EnemyAISystem;
+use crate::systems::GoldSystem;
+use crate::systems::LightingSystem;
 use crate::systems::MapIndexingSystem;
 use crate::systems::MeleeCombatSystem;
+use crate::systems::RangedCombatSystem;
+use crate::systems::StatusEffectSystem;
+use crate::systems::TeleportSystem;
+use crate::systems::TerrainSystem;
+use crate::systems::ThrowSystem;
+use crate::systems::TrapSystem;
 use crate::systems::VisibilitySystem;
 use crate::ui;
 
@@ -41,6 +56,16 @@ pub enum RunState {
     ShowInventory,
     // Displaying player drop menu
     ShowDropItem,
+    // Displaying player throw menu
+    ShowThrowItem,
+    // Picking where to throw an item
+    ThrowTargeting {
+        item: Entity,
+    },
+    // Aiming the equipped ranged weapon, cycling through what's in range
+    FireTargeting {
+        target: Entity,
+    },
     // Ranged item targeting UI
     ShowTargeting {
         range: i32,
@@ -54,6 +79,30 @@ pub enum RunState {
     SaveGame,
     // Player moving across map depths
     NextLevel,
+    // Replaying the steps the map generator took, before play starts
+    MapGeneration,
+    // Trading with a shopkeeper
+    Shop {
+        shopkeeper: Entity,
+        mode: ui::ShopMode,
+    },
+    // Revealing the map row by row after a magic mapping scroll is read
+    MagicMapReveal {
+        row: i32,
+    },
+}
+
+// How long each map generation snapshot stays on screen, in milliseconds
+const MAPGEN_FRAME_TIME: f32 = 150.0;
+
+/// The snapshots taken while generating the current map, and how far through replaying them we are
+#[derive(Default)]
+pub struct MapGenerationReplay {
+    // Set by the --show-mapgen flag
+    pub enabled: bool,
+    pub snapshots: Vec<Vec<CellType>>,
+    pub frame: usize,
+    pub timer: f32,
 }
 
 pub struct State {
@@ -62,18 +111,38 @@ pub struct State {
 
 impl State {
     fn run_systems(&mut self) {
+        // Must run before the visibility system, which only shows what's lit
+        let mut lighting_system = LightingSystem {};
+        lighting_system.run_now(&self.ecs);
+
         let mut vis_system = VisibilitySystem {};
         vis_system.run_now(&self.ecs);
 
         let mut enemy_ai_system = EnemyAISystem {};
         enemy_ai_system.run_now(&self.ecs);
 
+        let mut status_effect_system = StatusEffectSystem {};
+        status_effect_system.run_now(&self.ecs);
+
         let mut map_idx_system = MapIndexingSystem {};
         map_idx_system.run_now(&self.ecs);
 
+        // This and the gold system must run before the trap system, which clears EntityMoved
+        let mut terrain_system = TerrainSystem {};
+        terrain_system.run_now(&self.ecs);
+
+        let mut gold_system = GoldSystem {};
+        gold_system.run_now(&self.ecs);
+
+        let mut trap_system = TrapSystem {};
+        trap_system.run_now(&self.ecs);
+
         let mut melee_system = MeleeCombatSystem {};
         melee_system.run_now(&self.ecs);
 
+        let mut ranged_combat_system = RangedCombatSystem {};
+        ranged_combat_system.run_now(&self.ecs);
+
         let mut damage_system = DamageSystem {};
         damage_system.run_now(&self.ecs);
 
@@ -83,9 +152,19 @@ impl State {
         let mut item_use_system = ItemUseSystem {};
         item_use_system.run_now(&self.ecs);
 
+        let mut item_equip_system = ItemEquipSystem {};
+        item_equip_system.run_now(&self.ecs);
+
         let mut item_drop_system = ItemDropSystem {};
         item_drop_system.run_now(&self.ecs);
 
+        let mut throw_system = ThrowSystem {};
+        throw_system.run_now(&self.ecs);
+
+        // Traps, items and spells all ask for teleports, so this comes after them
+        let mut teleport_system = TeleportSystem {};
+        teleport_system.run_now(&self.ecs);
+
         self.ecs.maintain();
     }
 
@@ -121,43 +200,71 @@ impl State {
         to_delete
     }
 
-    fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or player's inventory
-        let to_delete = self.entities_to_remove_on_level_change();
-        for target in to_delete {
-            self.ecs
-                .delete_entity(target)
-                .expect("unable to delete entity");
+    /// Starts over with a new player on a new first level, under a fresh run id and save history, so nothing
+    /// carries over from whatever was played or loaded before
+    fn start_new_run(&mut self, ironman: bool) {
+        self.ecs.delete_all();
+
+        let (metadata, identification) = {
+            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            (
+                save::SaveMetadata::new_run(&mut rng, ironman),
+                Identification::new(&mut rng),
+            )
+        };
+
+        *self.ecs.write_resource::<save::SaveMetadata>() = metadata;
+        *self.ecs.write_resource::<Identification>() = identification;
+        self.ecs.write_resource::<save::AutosaveTimer>().turns = 0;
+        self.ecs.write_resource::<GameLog>().entries.clear();
+
+        let player = spawn::player(&mut self.ecs, 0, 0);
+        *self.ecs.write_resource::<Entity>() = player;
+
+        self.generate_world_map(1);
+    }
+
+    /// The state to start a freshly generated level in: replaying its generation if that's enabled, or straight
+    /// into play
+    fn new_level_state(&self) -> RunState {
+        if self.ecs.fetch::<MapGenerationReplay>().enabled {
+            RunState::MapGeneration
+        } else {
+            RunState::PreRun
         }
+    }
 
-        // Build new map and place player
-        let map;
-        let current_depth;
-        {
-            let mut existing_map = self.ecs.write_resource::<Map>();
-            current_depth = existing_map.depth;
+    /// Builds a new map for the given depth, fills it, and moves the player to its start
+    pub fn generate_world_map(&mut self, new_depth: i32) {
+        let mut builder = {
+            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            map_builders::build_level(new_depth, &mut rng)
+        };
 
-            *existing_map = Map::generate_map_rooms_and_tunnels(current_depth + 1);
-            map = existing_map.clone();
+        {
+            let mut replay = self.ecs.write_resource::<MapGenerationReplay>();
+            replay.snapshots = builder.get_snapshot_history();
+            replay.frame = 0;
+            replay.timer = 0.0;
         }
 
-        // Spawn enemies
-        for room in map.rooms.iter().skip(1) {
-            spawn::room(&mut self.ecs, room, current_depth + 1);
+        {
+            let mut map = self.ecs.write_resource::<Map>();
+            *map = builder.get_map();
         }
 
-        let (player_x, player_y) = map.rooms[0].center();
-        let mut player_pos = self.ecs.write_resource::<rltk::Point>();
+        builder.spawn_entities(&mut self.ecs);
 
-        *player_pos = Point::new(player_x, player_y);
+        let start = builder.get_starting_position();
+        let mut player_pos = self.ecs.write_resource::<rltk::Point>();
+        *player_pos = Point::new(start.x, start.y);
 
         let mut positions = self.ecs.write_storage::<Position>();
         let player = self.ecs.fetch::<Entity>();
 
         let player_pos_comp = positions.get_mut(*player);
         if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
+            *player_pos_comp = start;
         }
 
         // Mark player's position as dirty
@@ -166,6 +273,20 @@ impl State {
         if let Some(vs) = vs {
             vs.dirty = true;
         }
+    }
+
+    fn goto_next_level(&mut self) {
+        // Delete entities that aren't the player or player's inventory
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("unable to delete entity");
+        }
+
+        // Build new map and place player
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        self.generate_world_map(current_depth + 1);
 
         let mut log = self.ecs.fetch_mut::<GameLog>();
         log.entries
@@ -182,23 +303,16 @@ impl rltk::GameState for State {
         match run_state {
             RunState::MainMenu { .. } => {}
 
-            _ => {
-                draw_map(&self.ecs, ctx);
-
-                let map = self.ecs.fetch::<Map>();
-                let positions = self.ecs.read_storage::<Position>();
-                let renderables = self.ecs.read_storage::<Renderable>();
-
-                let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+            RunState::MapGeneration => {
+                let replay = self.ecs.fetch::<MapGenerationReplay>();
 
-                for (pos, render) in data.iter() {
-                    let idx = map.xy_idx(pos.x, pos.y);
-                    if map.visible_cells[idx] {
-                        ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
-                    }
+                if let Some(cells) = replay.snapshots.get(replay.frame) {
+                    camera::render_snapshot(&self.ecs, ctx, cells);
                 }
+            }
 
+            _ => {
+                camera::render_camera(&self.ecs, ctx);
                 ui::draw_ui(&self.ecs, ctx);
             }
         }
@@ -220,14 +334,32 @@ impl rltk::GameState for State {
                 self.run_systems();
                 self.ecs.maintain();
 
-                run_state = RunState::EnemyTurn;
+                // Systems may send the player elsewhere e.g. down a trapdoor
+                run_state = match *self.ecs.fetch::<RunState>() {
+                    RunState::NextLevel => RunState::NextLevel,
+                    RunState::MagicMapReveal { .. } => RunState::MagicMapReveal { row: 0 },
+                    _ => RunState::EnemyTurn,
+                };
             }
 
             RunState::EnemyTurn => {
                 self.run_systems();
                 self.ecs.maintain();
 
-                run_state = RunState::AwaitingInput;
+                run_state = match *self.ecs.fetch::<RunState>() {
+                    RunState::NextLevel => RunState::NextLevel,
+                    _ => RunState::AwaitingInput,
+                };
+
+                let autosave_due = {
+                    let mut timer = self.ecs.write_resource::<save::AutosaveTimer>();
+                    timer.turns += 1;
+                    timer.turns >= save::AUTOSAVE_INTERVAL
+                };
+
+                if autosave_due {
+                    save::save_game(&mut self.ecs);
+                }
             }
 
             RunState::ShowInventory => {
@@ -238,9 +370,24 @@ impl rltk::GameState for State {
                     ui::ItemMenuResult::Selected => {
                         let selected_item = result.1.unwrap();
                         let ranged = self.ecs.read_storage::<Ranged>();
+                        let equippable = self.ecs.read_storage::<Equippable>();
                         let maybe_ranged_item = ranged.get(selected_item);
 
-                        if let Some(ranged_item) = maybe_ranged_item {
+                        if equippable.get(selected_item).is_some() {
+                            let entity = self.ecs.fetch::<Entity>();
+                            let mut intent = self.ecs.write_storage::<DesiresEquipItem>();
+
+                            intent
+                                .insert(
+                                    *entity,
+                                    DesiresEquipItem {
+                                        item: selected_item,
+                                    },
+                                )
+                                .expect("unable to insert equip intent");
+
+                            run_state = RunState::PlayerTurn;
+                        } else if let Some(ranged_item) = maybe_ranged_item {
                             run_state = RunState::ShowTargeting {
                                 range: ranged_item.range,
                                 item: selected_item,
@@ -271,7 +418,7 @@ impl rltk::GameState for State {
                     ui::ItemMenuResult::NoResponse => {}
                     ui::ItemMenuResult::Cancel => run_state = RunState::AwaitingInput,
                     ui::ItemMenuResult::Selected => {
-                        let item = result.1.unwrap();
+                        let item = inventory::take_one_from_stack(&mut self.ecs, result.1.unwrap());
                         let player = self.ecs.fetch::<Entity>();
                         let mut intent = self.ecs.write_storage::<DesiresDropItem>();
 
@@ -284,6 +431,80 @@ impl rltk::GameState for State {
                 }
             }
 
+            RunState::ShowThrowItem => {
+                let result = ui::throw_item_menu(self, ctx);
+                match result.0 {
+                    ui::ItemMenuResult::NoResponse => {}
+                    ui::ItemMenuResult::Cancel => run_state = RunState::AwaitingInput,
+                    ui::ItemMenuResult::Selected => {
+                        run_state = RunState::ThrowTargeting {
+                            item: result.1.unwrap(),
+                        }
+                    }
+                }
+            }
+
+            RunState::ThrowTargeting { item } => {
+                let target = ui::ranged_target(self, ctx, throw::THROW_RANGE);
+
+                match target.0 {
+                    ui::ItemMenuResult::Cancel => run_state = RunState::AwaitingInput,
+                    ui::ItemMenuResult::NoResponse => {}
+                    ui::ItemMenuResult::Selected => {
+                        let item = inventory::take_one_from_stack(&mut self.ecs, item);
+                        let player = self.ecs.fetch::<Entity>();
+                        let mut intent = self.ecs.write_storage::<DesiresThrowItem>();
+
+                        intent
+                            .insert(
+                                *player,
+                                DesiresThrowItem {
+                                    item,
+                                    target: target.1.unwrap(),
+                                },
+                            )
+                            .expect("unable to insert throw intent");
+
+                        run_state = RunState::PlayerTurn;
+                    }
+                }
+            }
+
+            RunState::FireTargeting { target } => {
+                let player = *self.ecs.fetch::<Entity>();
+
+                match ui::fire_target(self, ctx, target) {
+                    ui::FireTargetResult::Cancel => run_state = RunState::AwaitingInput,
+                    ui::FireTargetResult::NoResponse => {}
+                    ui::FireTargetResult::NextTarget => {
+                        let targets = ranged_combat::fire_targets(&self.ecs, player);
+                        let next = targets
+                            .iter()
+                            .position(|candidate| *candidate == target)
+                            .map_or(0, |current| (current + 1) % targets.len());
+
+                        if let Some(next) = targets.get(next) {
+                            run_state = RunState::FireTargeting { target: *next };
+                        }
+                    }
+                    ui::FireTargetResult::Fire => {
+                        match ranged_combat::loaded_ammo(&self.ecs, player) {
+                            Some(ammo) => {
+                                let ammo = inventory::take_one_from_stack(&mut self.ecs, ammo);
+                                let mut intent = self.ecs.write_storage::<DesiresFire>();
+
+                                intent
+                                    .insert(player, DesiresFire { ammo, target })
+                                    .expect("unable to insert fire intent");
+
+                                run_state = RunState::PlayerTurn;
+                            }
+                            None => run_state = RunState::AwaitingInput,
+                        }
+                    }
+                }
+            }
+
             RunState::ShowTargeting { range, item } => {
                 let target = ui::ranged_target(self, ctx, range);
 
@@ -320,12 +541,28 @@ impl rltk::GameState for State {
                     }
 
                     ui::MainMenuResult::Selected { selected } => match selected {
-                        ui::MainMenuSelection::NewGame => run_state = RunState::PreRun,
+                        ui::MainMenuSelection::NewGame => {
+                            self.start_new_run(false);
+                            run_state = self.new_level_state();
+                        }
+                        ui::MainMenuSelection::NewIronmanGame => {
+                            self.start_new_run(true);
+                            run_state = self.new_level_state();
+                        }
                         ui::MainMenuSelection::LoadGame => {
-                            save::load_game(&mut self.ecs);
-                            run_state = RunState::PreRun;
-                            // TODO: if we want perma-death
-                            // save::delete_save();
+                            // Ironman saves are deleted here, and rewritten on quit
+                            match save::load_game(&mut self.ecs) {
+                                Ok(()) => run_state = RunState::PreRun,
+                                Err(_) => {
+                                    let mut log = self.ecs.write_resource::<GameLog>();
+                                    log.entries.clear();
+                                    log.entries.push("The save is unreadable.".to_string());
+
+                                    run_state = RunState::MainMenu {
+                                        menu_selection: ui::MainMenuSelection::NewGame,
+                                    };
+                                }
+                            }
                         }
                         ui::MainMenuSelection::Quit => {
                             ::std::process::exit(0);
@@ -344,7 +581,66 @@ impl rltk::GameState for State {
 
             RunState::NextLevel => {
                 self.goto_next_level();
-                run_state = RunState::PreRun;
+                save::save_game(&mut self.ecs);
+                run_state = self.new_level_state();
+            }
+
+            RunState::Shop { shopkeeper, mode } => {
+                let result = ui::shop_menu(self, ctx, shopkeeper, mode);
+
+                match result.0 {
+                    ui::ShopMenuResult::NoResponse => {}
+                    ui::ShopMenuResult::Cancel => run_state = RunState::AwaitingInput,
+                    ui::ShopMenuResult::SwitchMode => {
+                        let mode = match mode {
+                            ui::ShopMode::Buy => ui::ShopMode::Sell,
+                            ui::ShopMode::Sell => ui::ShopMode::Buy,
+                        };
+
+                        run_state = RunState::Shop { shopkeeper, mode };
+                    }
+                    ui::ShopMenuResult::Selected => {
+                        let item = result.1.unwrap();
+                        let player = *self.ecs.fetch::<Entity>();
+
+                        match mode {
+                            ui::ShopMode::Buy => shop::buy(&mut self.ecs, player, shopkeeper, item),
+                            ui::ShopMode::Sell => {
+                                shop::sell(&mut self.ecs, player, shopkeeper, item)
+                            }
+                        }
+                        self.ecs.maintain();
+                    }
+                }
+            }
+
+            RunState::MagicMapReveal { row } => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+
+                for x in 0..map.width {
+                    let idx = map.xy_idx(x, row);
+                    map.revealed_cells[idx] = true;
+                }
+
+                if row >= map.height - 1 {
+                    run_state = RunState::EnemyTurn;
+                } else {
+                    run_state = RunState::MagicMapReveal { row: row + 1 };
+                }
+            }
+
+            RunState::MapGeneration => {
+                let mut replay = self.ecs.write_resource::<MapGenerationReplay>();
+
+                replay.timer += ctx.frame_time_ms;
+                if replay.timer > MAPGEN_FRAME_TIME {
+                    replay.timer = 0.0;
+                    replay.frame += 1;
+                }
+
+                if replay.frame >= replay.snapshots.len() {
+                    run_state = RunState::PreRun;
+                }
             }
         }
 
@@ -1,20 +1,33 @@
 use specs::prelude::*;
 
 use crate::{
-    components::{CombatStats, Damage, Name, Player},
+    components::{CombatStats, Damage, Name, Player, StatusEffect, StatusEffectType},
     log,
 };
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
-    type SystemData = (WriteStorage<'a, CombatStats>, WriteStorage<'a, Damage>);
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, StatusEffect>,
+    );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut stats, mut damage, mut status_effects) = data;
 
-        for (stats, damage) in (&mut stats, &damage).join() {
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
             stats.hp -= damage.amount.iter().sum::<i32>();
+
+            // Getting hurt wakes anyone up
+            if status_effects
+                .get(entity)
+                .is_some_and(|effect| matches!(effect.effect, StatusEffectType::Sleep))
+            {
+                status_effects.remove(entity);
+            }
         }
 
         damage.clear();
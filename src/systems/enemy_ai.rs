@@ -1,12 +1,22 @@
-use rltk::Point;
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
 
 use crate::{
-    components::{DesiresMelee, Enemy, Position, StatusEffect, StatusEffectType, Viewshed},
+    components::{
+        Alerted, DesiresMelee, DesiresTeleport, Enemy, EntityMoved, OpensDoors, Position,
+        StatusEffect, TeleportSpell, Viewshed,
+    },
     map::Map,
     state::RunState,
+    systems::teleport,
 };
 
+// Enemies that can blink have a 1 in this chance of doing so each turn they see the player
+const BLINK_CHANCE: i32 = 3;
+
+// Enemies only notice a player standing in darkness from this close
+const DARK_NOTICE_RANGE: f32 = 2.5;
+
 pub struct EnemyAISystem {}
 
 impl<'a> System<'a> for EnemyAISystem {
@@ -16,11 +26,17 @@ impl<'a> System<'a> for EnemyAISystem {
         ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
         ReadStorage<'a, Enemy>,
+        ReadStorage<'a, OpensDoors>,
+        ReadStorage<'a, Alerted>,
+        ReadStorage<'a, TeleportSpell>,
+        ReadStorage<'a, StatusEffect>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteExpect<'a, Map>,
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, DesiresMelee>,
-        WriteStorage<'a, StatusEffect>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, DesiresTeleport>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -30,51 +46,89 @@ impl<'a> System<'a> for EnemyAISystem {
             player,
             run_state,
             enemy,
+            opens_doors,
+            alerted,
+            teleport_spells,
+            status_effects,
+            mut rng,
             mut map,
             mut viewsheds,
             mut positions,
             mut desires_melee,
-            mut status_effects,
+            mut entity_moved,
+            mut desires_teleport,
         ) = data;
 
         if *run_state != RunState::EnemyTurn {
             return;
         }
 
+        let mut opened_door = false;
+
         for (entity, viewshed, _enemy, pos) in
             (&entities, &mut viewsheds, &enemy, &mut positions).join()
         {
-            let mut can_act = true;
-
-            let status_effect = status_effects.get_mut(entity);
-            if let Some(effect) = status_effect {
-                can_act = match effect.effect {
-                    StatusEffectType::Confusion => false,
-                };
-
-                effect.turns -= 1;
-                if effect.turns < 1 {
-                    status_effects.remove(entity);
-                }
-            }
+            // Confusion and sleep both keep an enemy from doing anything
+            let can_act = status_effects.get(entity).is_none();
 
             if can_act {
                 let distance =
                     rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
 
+                // A player in the dark can slip past anything not right on top of them
+                let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+                let can_see_player = viewshed.visible_cells.contains(&*player_pos)
+                    && (map.is_lit(player_idx) || distance <= DARK_NOTICE_RANGE);
+
+                // Casters out of reach may blink to the player's side instead of walking
+                let blink_to = match teleport_spells.get(entity) {
+                    Some(spell)
+                        if can_see_player
+                            && distance >= 1.5
+                            && distance <= spell.range as f32
+                            && rng.roll_dice(1, BLINK_CHANCE) == 1 =>
+                    {
+                        open_cell_beside(&map, *player_pos, &mut rng)
+                    }
+                    _ => None,
+                };
+
                 if distance < 1.5 {
                     desires_melee
                         .insert(entity, DesiresMelee { target: *player })
                         .expect("unable to insert attack");
-                } else if viewshed.visible_cells.contains(&*player_pos) {
+                } else if let Some(dest) = blink_to {
+                    desires_teleport
+                        .insert(
+                            entity,
+                            DesiresTeleport {
+                                destination: Some(dest),
+                            },
+                        )
+                        .expect("unable to insert teleport");
+                } else if can_see_player || alerted.get(entity).is_some() {
+                    let can_open_doors = opens_doors.get(entity).is_some();
+
+                    // Let entities that can open doors path through them
+                    if can_open_doors {
+                        map.set_closed_doors_blocked(false);
+                    }
+
                     let path = rltk::a_star_search(
                         map.xy_idx(pos.x, pos.y) as i32,
                         map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map,
+                        &*map,
                     );
 
-                    // Check for 2+ steps (where 0 is current location) and move enemy to that location
-                    if path.success && path.steps.len() > 1 {
+                    if can_open_doors {
+                        map.set_closed_doors_blocked(true);
+                    }
+
+                    // Opening a door in the way takes the enemy's turn
+                    if path.success && path.steps.len() > 1 && map.open_door(path.steps[1]) {
+                        opened_door = true;
+                    } else if path.success && path.steps.len() > 1 {
+                        // Check for 2+ steps (where 0 is current location) and move enemy to that location
                         let mut idx = map.xy_idx(pos.x, pos.y);
 
                         map.blocked[idx] = false;
@@ -86,9 +140,39 @@ impl<'a> System<'a> for EnemyAISystem {
 
                         map.blocked[idx] = true;
                         viewshed.dirty = true;
+
+                        entity_moved
+                            .insert(entity, EntityMoved {})
+                            .expect("unable to insert moved marker");
                     }
                 }
             }
         }
+
+        // An opened door changes what everyone can see
+        if opened_door {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
     }
 }
+
+/// A random open cell next to the given point, if there is one
+fn open_cell_beside(map: &Map, point: Point, rng: &mut RandomNumberGenerator) -> Option<Point> {
+    let mut open: Vec<Point> = Vec::new();
+
+    for y in point.y - 1..=point.y + 1 {
+        for x in point.x - 1..=point.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            if teleport::is_open_cell(map, map.xy_idx(x, y)) {
+                open.push(Point::new(x, y));
+            }
+        }
+    }
+
+    rng.random_slice_entry(&open).copied()
+}
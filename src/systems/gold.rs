@@ -0,0 +1,45 @@
+use specs::prelude::*;
+
+use crate::{
+    components::{EntityMoved, GoldPile, Position, Purse},
+    log::GameLog,
+    map::Map,
+};
+
+/// Scoops any gold in their way into the purses of those who moved
+pub struct GoldSystem {}
+
+impl<'a> System<'a> for GoldSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, EntityMoved>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, GoldPile>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, Purse>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player, map, moved, positions, piles, mut log, mut purses) = data;
+
+        for (entity, _moved, pos, purse) in (&entities, &moved, &positions, &mut purses).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+
+            for content in map.cell_content[idx].iter() {
+                if let Some(pile) = piles.get(*content) {
+                    purse.gold += pile.amount;
+                    entities
+                        .delete(*content)
+                        .expect("unable to delete gold pile");
+
+                    if entity == *player {
+                        log.entries
+                            .push(format!("You pick up {} gold", pile.amount));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,29 +2,196 @@ use specs::prelude::*;
 
 use crate::{
     components::{
-        AreaOfEffect, CombatStats, Consumable, Damage, DesiresAcquireItem, DesiresDropItem,
-        DesiresUseItem, InInventory, InflictsDamage, Name, Position, ProvidesHealing, StatusEffect,
+        AreaOfEffect, Attributes, Charges, CombatStats, Consumable, Cursed, Damage,
+        DesiresAcquireItem, DesiresDropItem, DesiresEquipItem, DesiresTeleport, DesiresUseItem,
+        DetectsMonsters, Equippable, Equipped, Identifies, InInventory, InflictsDamage,
+        MagicMapper, Name, Position, ProvidesHealing, Recharges, RemovesCurse, Stackable,
+        StatusEffect, Teleports, Weight,
     },
+    identification::Identification,
     log::GameLog,
     map::Map,
+    registry,
+    state::RunState,
 };
 
+/// Takes one item off the given stack, returning it as an entity of its own. Anything that isn't stacked, or is
+/// the last of its stack, is returned as is
+pub fn take_one_from_stack(ecs: &mut World, item: Entity) -> Entity {
+    let quantity = ecs
+        .read_storage::<Stackable>()
+        .get(item)
+        .map_or(1, |stack| stack.quantity);
+
+    if quantity <= 1 {
+        return item;
+    }
+
+    let single = registry::clone_entity(ecs, item);
+    let mut stacks = ecs.write_storage::<Stackable>();
+
+    stacks.get_mut(item).unwrap().quantity -= 1;
+    stacks.get_mut(single).unwrap().quantity = 1;
+
+    single
+}
+
+/// What can set apart two items of the same name, so that only truly alike items share a stack
+pub struct ItemDetails<'s, 'a> {
+    pub names: &'s ReadStorage<'a, Name>,
+    pub charges: &'s ReadStorage<'a, Charges>,
+    pub cursed: &'s ReadStorage<'a, Cursed>,
+}
+
+impl ItemDetails<'_, '_> {
+    /// Whether the two items share a name, charges and curse
+    pub fn alike(&self, item: Entity, other: Entity) -> bool {
+        self.names.get(item).map(|name| &name.name) == self.names.get(other).map(|name| &name.name)
+            && self.charges.get(item) == self.charges.get(other)
+            && self.cursed.get(item) == self.cursed.get(other)
+    }
+}
+
+/// Merges a stackable item into a stack of the same thing the owner already carries, deleting it. Returns whether
+/// there was such a stack
+pub fn merge_into_stack(
+    entities: &Entities,
+    details: &ItemDetails,
+    inventory: &WriteStorage<InInventory>,
+    stacks: &mut WriteStorage<Stackable>,
+    item: Entity,
+    owner: Entity,
+) -> bool {
+    let quantity = match stacks.get(item) {
+        Some(stack) => stack.quantity,
+        None => return false,
+    };
+
+    let existing_stack = (entities, inventory, &*stacks)
+        .join()
+        .find(|(entity, carried, _)| {
+            *entity != item && carried.owner == owner && details.alike(item, *entity)
+        })
+        .map(|(entity, _, _)| entity);
+
+    match existing_stack {
+        Some(existing) => {
+            stacks.get_mut(existing).unwrap().quantity += quantity;
+            entities.delete(item).expect("unable to delete merged item");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Puts the item straight into the owner's inventory, merging it into a stack of the same thing if they have
+/// one. Unlike picking something up, this takes no turn and ignores how much the owner can carry
+pub fn add_to_inventory(ecs: &mut World, item: Entity, owner: Entity) {
+    let entities = ecs.entities();
+    let details = ItemDetails {
+        names: &ecs.read_storage::<Name>(),
+        charges: &ecs.read_storage::<Charges>(),
+        cursed: &ecs.read_storage::<Cursed>(),
+    };
+    let mut inventory = ecs.write_storage::<InInventory>();
+    let mut stacks = ecs.write_storage::<Stackable>();
+
+    ecs.write_storage::<Position>().remove(item);
+
+    if merge_into_stack(&entities, &details, &inventory, &mut stacks, item, owner) {
+        return;
+    }
+
+    inventory
+        .insert(item, InInventory { owner })
+        .expect("unable to insert into inventory");
+}
+
 pub struct ItemAcquisitionSystem {}
 
 impl<'a> System<'a> for ItemAcquisitionSystem {
     type SystemData = (
+        Entities<'a>,
         ReadExpect<'a, Entity>,
+        ReadExpect<'a, Identification>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Charges>,
+        ReadStorage<'a, Cursed>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, DesiresAcquireItem>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InInventory>,
+        WriteStorage<'a, Stackable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player, names, mut log, mut desires_item, mut positions, mut inventory) = data;
+        let (
+            entities,
+            player,
+            identification,
+            names,
+            attributes,
+            weights,
+            charges,
+            cursed,
+            mut log,
+            mut desires_item,
+            mut positions,
+            mut inventory,
+            mut stacks,
+        ) = data;
 
         for acquisition in desires_item.join() {
+            // Anything without attributes has no limit to what it can carry
+            if let Some(attributes) = attributes.get(acquisition.acquired_by) {
+                let carried: i32 = (&entities, &inventory, &weights)
+                    .join()
+                    .filter(|(_, carried, _)| carried.owner == acquisition.acquired_by)
+                    .map(|(item, _, weight)| weight.of_stack(stacks.get(item)))
+                    .sum();
+                let picking_up = weights
+                    .get(acquisition.item)
+                    .map_or(0, |weight| weight.of_stack(stacks.get(acquisition.item)));
+
+                if carried + picking_up > attributes.carrying_capacity() {
+                    if acquisition.acquired_by == *player {
+                        log.entries.push(format!(
+                            "You can't carry the {}, your pack is too heavy",
+                            identification.name_of(names.get(acquisition.item).unwrap())
+                        ));
+                    }
+                    continue;
+                }
+            }
+
+            if acquisition.acquired_by == *player {
+                log.entries.push(format!(
+                    "You pick up the {}",
+                    identification.name_of(names.get(acquisition.item).unwrap())
+                ));
+            }
+
+            positions.remove(acquisition.item);
+
+            // A stackable item joins any stack of the same thing already carried
+            let details = ItemDetails {
+                names: &names,
+                charges: &charges,
+                cursed: &cursed,
+            };
+            if merge_into_stack(
+                &entities,
+                &details,
+                &inventory,
+                &mut stacks,
+                acquisition.item,
+                acquisition.acquired_by,
+            ) {
+                continue;
+            }
+
             inventory
                 .insert(
                     acquisition.item,
@@ -33,15 +200,6 @@ impl<'a> System<'a> for ItemAcquisitionSystem {
                     },
                 )
                 .expect("failed cx to insert into inventory");
-
-            positions.remove(acquisition.item);
-
-            if acquisition.acquired_by == *player {
-                log.entries.push(format!(
-                    "You pick up the {}",
-                    names.get(acquisition.item).unwrap().name
-                ));
-            }
         }
 
         desires_item.clear();
@@ -60,11 +218,24 @@ impl<'a> System<'a> for ItemUseSystem {
         ReadStorage<'a, ProvidesHealing>,
         ReadStorage<'a, InflictsDamage>,
         ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, MagicMapper>,
+        ReadStorage<'a, Teleports>,
+        ReadStorage<'a, Recharges>,
+        ReadStorage<'a, Identifies>,
+        ReadStorage<'a, RemovesCurse>,
+        ReadStorage<'a, InInventory>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RunState>,
+        WriteExpect<'a, Identification>,
         WriteStorage<'a, DesiresUseItem>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, Damage>,
         WriteStorage<'a, StatusEffect>,
+        WriteStorage<'a, DetectsMonsters>,
+        WriteStorage<'a, DesiresTeleport>,
+        WriteStorage<'a, Charges>,
+        WriteStorage<'a, Cursed>,
+        WriteStorage<'a, Stackable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -77,14 +248,53 @@ impl<'a> System<'a> for ItemUseSystem {
             heals,
             damages,
             aoe,
+            magic_mappers,
+            teleports,
+            rechargers,
+            identifiers,
+            curse_removers,
+            in_inventory,
             mut log,
+            mut run_state,
+            mut identification,
             mut desires_use,
             mut combat_stats,
             mut damage,
             mut status_effects,
+            mut detections,
+            mut desires_teleport,
+            mut charges,
+            mut curses,
+            mut stacks,
         ) = data;
 
         for (acting_entity, use_intent) in (&entities, &desires_use).join() {
+            let item_name = identification.name_of(names.get(use_intent.item).unwrap());
+
+            // An empty wand does nothing, though trying it still takes the turn
+            if charges
+                .get(use_intent.item)
+                .is_some_and(|charge| charge.current < 1)
+            {
+                if acting_entity == *player {
+                    log.entries
+                        .push(format!("The {} is out of charges.", item_name));
+                }
+                continue;
+            }
+
+            // Using something is the surest way to learn what it is
+            if acting_entity == *player
+                && identification.identify(names.get(use_intent.item).unwrap())
+            {
+                log.entries.push(format!(
+                    "You identify the {} as the {}.",
+                    item_name,
+                    names.get(use_intent.item).unwrap().name
+                ));
+            }
+            let item_name = identification.name_of(names.get(use_intent.item).unwrap());
+
             let mut used_item = false;
             let mut targets: Vec<Entity> = Vec::new();
 
@@ -137,8 +347,7 @@ impl<'a> System<'a> for ItemUseSystem {
                             if acting_entity == *player {
                                 log.entries.push(format!(
                                     "You consume the {}, healing {} hp",
-                                    &names.get(use_intent.item).unwrap().name,
-                                    healer.heal_amount
+                                    item_name, healer.heal_amount
                                 ));
                                 used_item = true;
                             }
@@ -157,12 +366,11 @@ impl<'a> System<'a> for ItemUseSystem {
                         Damage::new_damage(&mut damage, *target, damager.damage);
 
                         if acting_entity == *player {
-                            let target_name = names.get(*target).unwrap();
-                            let item_name = names.get(use_intent.item).unwrap();
+                            let target_name = identification.name_of(names.get(*target).unwrap());
 
                             log.entries.push(format!(
                                 "You use {} on {}, inflicting {} hp",
-                                item_name.name, target_name.name, damager.damage
+                                item_name, target_name, damager.damage
                             ))
                         }
 
@@ -171,6 +379,104 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            // The reveal itself is animated by the game state
+            if magic_mappers.get(use_intent.item).is_some() {
+                log.entries.push("The map is revealed to you!".to_string());
+                *run_state = RunState::MagicMapReveal { row: 0 };
+                used_item = true;
+            }
+
+            // Ranged teleports land on the chosen cell, the rest anywhere
+            if teleports.get(use_intent.item).is_some() {
+                desires_teleport
+                    .insert(
+                        acting_entity,
+                        DesiresTeleport {
+                            destination: use_intent.target,
+                        },
+                    )
+                    .expect("unable to insert teleport");
+                used_item = true;
+            }
+
+            if rechargers.get(use_intent.item).is_some() {
+                let mut recharged = 0;
+
+                for (charge, carried) in (&mut charges, &in_inventory).join() {
+                    if carried.owner == acting_entity {
+                        charge.current = charge.max;
+                        recharged += 1;
+                    }
+                }
+
+                if acting_entity == *player {
+                    if recharged > 0 {
+                        log.entries
+                            .push("Your wands hum with renewed power.".to_string());
+                    } else {
+                        log.entries
+                            .push("You feel a faint hum, but nothing happens.".to_string());
+                    }
+                }
+                used_item = true;
+            }
+
+            if identifiers.get(use_intent.item).is_some() {
+                let mut learned: Vec<String> = Vec::new();
+
+                for (item, carried, name) in (&entities, &in_inventory, &names).join() {
+                    if carried.owner == acting_entity {
+                        let appearance = identification.name_of(name);
+
+                        if identification.identify(name) {
+                            learned.push(format!(
+                                "You identify the {} as the {}.",
+                                appearance, name.name
+                            ));
+                        }
+
+                        if let Some(curse) = curses.get_mut(item) {
+                            if !curse.known {
+                                curse.known = true;
+                                learned.push(format!("The {} is cursed!", name.name));
+                            }
+                        }
+                    }
+                }
+
+                if acting_entity == *player {
+                    if learned.is_empty() {
+                        log.entries.push("You learn nothing new.".to_string());
+                    }
+
+                    log.entries.append(&mut learned);
+                }
+                used_item = true;
+            }
+
+            if curse_removers.get(use_intent.item).is_some() {
+                let lifted: Vec<Entity> = (&entities, &in_inventory, &curses)
+                    .join()
+                    .filter(|(_, carried, _)| carried.owner == acting_entity)
+                    .map(|(item, _, _)| item)
+                    .collect();
+
+                for item in lifted.iter() {
+                    curses.remove(*item);
+                }
+
+                if acting_entity == *player {
+                    if lifted.is_empty() {
+                        log.entries
+                            .push("You feel a moment of relief, but nothing happens.".to_string());
+                    } else {
+                        log.entries
+                            .push("You feel as if someone is watching over you.".to_string());
+                    }
+                }
+                used_item = true;
+            }
+
             let mut affected_targets = Vec::new();
             {
                 let effect = status_effects.get(use_intent.item);
@@ -184,13 +490,20 @@ impl<'a> System<'a> for ItemUseSystem {
                             affected_targets.push((*target, effect.clone()));
 
                             if acting_entity == *player {
-                                let target_name = names.get(*target).unwrap();
-                                let item_name = names.get(use_intent.item).unwrap();
-
-                                log.entries.push(format!(
-                                    "You use {} on {}, {} them.",
-                                    item_name.name, target_name.name, effect.print_as
-                                ));
+                                let target_name =
+                                    identification.name_of(names.get(*target).unwrap());
+
+                                if *target == *player {
+                                    log.entries.push(format!(
+                                        "You use {}, {} yourself.",
+                                        item_name, effect.print_as
+                                    ));
+                                } else {
+                                    log.entries.push(format!(
+                                        "You use {} on {}, {} them.",
+                                        item_name, target_name, effect.print_as
+                                    ));
+                                }
                             }
 
                             used_item = true;
@@ -205,15 +518,50 @@ impl<'a> System<'a> for ItemUseSystem {
                     .expect("failed to insert effect");
             }
 
-            if used_item {
-                let consumable = consumables.get(use_intent.item);
-                match consumable {
-                    Some(_) => {
+            if let Some(detection) = detections.get(use_intent.item).cloned() {
+                for target in targets.iter() {
+                    detections
+                        .insert(*target, detection.clone())
+                        .expect("failed to insert monster detection");
+                }
+
+                if acting_entity == *player && targets.contains(&*player) {
+                    log.entries.push(format!(
+                        "You use {}, attuning yourself to the monsters nearby.",
+                        item_name
+                    ));
+                }
+                used_item = !targets.is_empty();
+            }
+
+            if !used_item {
+                continue;
+            }
+
+            if let Some(charge) = charges.get_mut(use_intent.item) {
+                charge.current -= 1;
+
+                if charge.current < 1 {
+                    if charge.destroyed_when_empty {
                         entities
                             .delete(use_intent.item)
                             .expect("delete item failed");
                     }
-                    None => {}
+
+                    if acting_entity == *player {
+                        log.entries.push(if charge.destroyed_when_empty {
+                            format!("The {} crumbles to dust.", item_name)
+                        } else {
+                            format!("The {} goes dark.", item_name)
+                        });
+                    }
+                }
+            } else if consumables.get(use_intent.item).is_some() {
+                match stacks.get_mut(use_intent.item) {
+                    Some(stack) if stack.quantity > 1 => stack.quantity -= 1,
+                    _ => entities
+                        .delete(use_intent.item)
+                        .expect("delete item failed"),
                 }
             }
         }
@@ -222,24 +570,150 @@ impl<'a> System<'a> for ItemUseSystem {
     }
 }
 
+pub struct ItemEquipSystem {}
+
+impl<'a> System<'a> for ItemEquipSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Equippable>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, DesiresEquipItem>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Cursed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            names,
+            equippables,
+            mut log,
+            mut desires_equip,
+            mut equipped,
+            mut curses,
+        ) = data;
+
+        for (entity, equip_intent) in (&entities, &desires_equip).join() {
+            let slot = match equippables.get(equip_intent.item) {
+                Some(equippable) => equippable.slot,
+                None => continue,
+            };
+
+            // Taking something off, either on its own or to make room, is where a curse bites
+            let taking_off: Option<Entity> = if equipped.get(equip_intent.item).is_some() {
+                Some(equip_intent.item)
+            } else {
+                (&entities, &equipped)
+                    .join()
+                    .find(|(_, worn)| worn.owner == entity && worn.slot == slot)
+                    .map(|(item, _)| item)
+            };
+
+            if let Some(item) = taking_off {
+                if let Some(curse) = curses.get_mut(item) {
+                    curse.known = true;
+
+                    if entity == *player {
+                        log.entries.push(format!(
+                            "You can't remove the {}, it's cursed!",
+                            names.get(item).unwrap().name
+                        ));
+                    }
+                    continue;
+                }
+
+                equipped.remove(item);
+
+                if entity == *player {
+                    log.entries.push(format!(
+                        "You unequip the {}.",
+                        names.get(item).unwrap().name
+                    ));
+                }
+
+                if item == equip_intent.item {
+                    continue;
+                }
+            }
+
+            equipped
+                .insert(
+                    equip_intent.item,
+                    Equipped {
+                        owner: entity,
+                        slot,
+                    },
+                )
+                .expect("unable to insert equipped");
+
+            if entity == *player {
+                let item_name = &names.get(equip_intent.item).unwrap().name;
+                log.entries.push(format!("You equip the {}.", item_name));
+
+                if let Some(curse) = curses.get_mut(equip_intent.item) {
+                    curse.known = true;
+                    log.entries.push(format!(
+                        "The {} binds itself to you. It's cursed!",
+                        item_name
+                    ));
+                }
+            }
+        }
+
+        desires_equip.clear();
+    }
+}
+
 pub struct ItemDropSystem {}
 
 impl<'a> System<'a> for ItemDropSystem {
     type SystemData = (
         Entities<'a>,
         ReadExpect<'a, Entity>,
+        ReadExpect<'a, Identification>,
         ReadStorage<'a, Name>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, DesiresDropItem>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InInventory>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Cursed>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, player, names, mut log, mut desires_drop, mut positions, mut inventory) =
-            data;
+        let (
+            entities,
+            player,
+            identification,
+            names,
+            mut log,
+            mut desires_drop,
+            mut positions,
+            mut inventory,
+            mut equipped,
+            mut curses,
+        ) = data;
 
         for (entity, drop_intent) in (&entities, &desires_drop).join() {
+            if equipped.get(drop_intent.item).is_some() {
+                if let Some(curse) = curses.get_mut(drop_intent.item) {
+                    curse.known = true;
+
+                    if entity == *player {
+                        log.entries.push(format!(
+                            "You can't let go of the {}, it's cursed!",
+                            names.get(drop_intent.item).unwrap().name
+                        ));
+                    }
+                    continue;
+                }
+
+                equipped.remove(drop_intent.item);
+            }
+
             let current_pos = positions.get(entity).unwrap();
             let mut drop_pos = Position { x: 0, y: 0 };
 
@@ -255,7 +729,7 @@ impl<'a> System<'a> for ItemDropSystem {
             if entity == *player {
                 log.entries.push(format!(
                     "You dropped the {}",
-                    names.get(drop_intent.item).unwrap().name,
+                    identification.name_of(names.get(drop_intent.item).unwrap()),
                 ));
             }
         }
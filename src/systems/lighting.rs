@@ -0,0 +1,79 @@
+use rltk::{field_of_view, Point};
+use specs::prelude::*;
+
+use crate::{
+    components::{LightSource, Position, Viewshed},
+    map::Map,
+};
+
+/// Recomputes the light map each turn. Anything that could see a cell which has gone light or dark since has its
+/// viewshed marked dirty, since what the player can make out depends on the light
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, lights, mut viewsheds) = data;
+
+        let was_lit: Vec<bool> = (0..map.light.len()).map(|idx| map.is_lit(idx)).collect();
+
+        let count = (map.width * map.height) as usize;
+        map.light = vec![rltk::RGB::from_f32(0., 0., 0.); count];
+
+        for (pos, light) in (&positions, &lights).join() {
+            if !light.lit {
+                continue;
+            }
+
+            let origin = Point::new(pos.x, pos.y);
+            let lit_cells = field_of_view(origin, light.radius, &*map);
+
+            for cell in lit_cells.iter() {
+                if cell.x < 0 || cell.x >= map.width || cell.y < 0 || cell.y >= map.height {
+                    continue;
+                }
+
+                // Light fades out towards the edge of the radius
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, *cell);
+                let intensity = 1. - distance / (light.radius as f32 + 1.);
+                if intensity <= 0. {
+                    continue;
+                }
+
+                let idx = map.xy_idx(cell.x, cell.y);
+                let current = map.light[idx];
+
+                map.light[idx] = rltk::RGB::from_f32(
+                    (current.r + light.color.r * intensity).min(1.),
+                    (current.g + light.color.g * intensity).min(1.),
+                    (current.b + light.color.b * intensity).min(1.),
+                );
+            }
+        }
+
+        let changed: Vec<Point> = (0..count)
+            .filter(|idx| was_lit.get(*idx) != Some(&map.is_lit(*idx)))
+            .map(|idx| Point::new(idx as i32 % map.width, idx as i32 / map.width))
+            .collect();
+
+        if changed.is_empty() {
+            return;
+        }
+
+        for (viewshed, pos) in (&mut viewsheds, &positions).join() {
+            let origin = Point::new(pos.x, pos.y);
+
+            if changed.iter().any(|cell| {
+                rltk::DistanceAlg::Pythagoras.distance2d(origin, *cell) <= viewshed.range as f32
+            }) {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
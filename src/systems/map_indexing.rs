@@ -1,7 +1,7 @@
 use specs::prelude::*;
 
 use crate::{
-    components::{BlocksCell, Position},
+    components::{BlocksCell, Hidden, Position, Trap},
     map::Map,
 };
 
@@ -12,15 +12,26 @@ impl<'a> System<'a> for MapIndexingSystem {
         Entities<'a>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksCell>,
+        ReadStorage<'a, Trap>,
+        ReadStorage<'a, Hidden>,
         WriteExpect<'a, Map>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, positions, blockers, mut map) = data;
+        let (entities, positions, blockers, traps, hidden, mut map) = data;
 
         map.populate_blocked();
         map.clear_content_idx();
 
+        for cell in map.revealed_traps.iter_mut() {
+            *cell = false;
+        }
+
+        for (_trap, pos, _hidden) in (&traps, &positions, !&hidden).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            map.revealed_traps[idx] = true;
+        }
+
         for (entity, pos) in (&entities, &positions).join() {
             let idx = map.xy_idx(pos.x, pos.y);
 
@@ -1,10 +1,17 @@
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
 use crate::{
-    components::{CombatStats, Damage, DesiresMelee, Name},
+    combat::{self, AttackOutcome},
+    components::{
+        CombatStats, Damage, DamageDice, DefenseBonus, DesiresMelee, Equipped, MeleePowerBonus,
+        Name,
+    },
     log::GameLog,
 };
 
+/// Settles melee attacks with a d20 roll plus the attacker's power against the target's armor class. Landed
+/// blows roll the wielded weapon's damage dice, or hit unarmed
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
@@ -12,15 +19,32 @@ impl<'a> System<'a> for MeleeCombatSystem {
         Entities<'a>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, DamageDice>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, DesiresMelee>,
         WriteStorage<'a, Damage>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, names, combat_stats, mut log, mut desires_melee, mut damages) = data;
+        let (
+            entities,
+            names,
+            combat_stats,
+            equipped,
+            power_bonuses,
+            defense_bonuses,
+            damage_dice,
+            mut rng,
+            mut log,
+            mut desires_melee,
+            mut damages,
+        ) = data;
 
-        for (_entity, melee_intent, name, stats) in
+        for (entity, melee_intent, name, stats) in
             (&entities, &desires_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
@@ -28,18 +52,57 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 if target_stats.hp > 0 {
                     let target_name = names.get(melee_intent.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{} did 0 damage to {}",
+                    let power_bonus: i32 = (&equipped, &power_bonuses)
+                        .join()
+                        .filter(|(worn, _)| worn.owner == entity)
+                        .map(|(_, bonus)| bonus.power)
+                        .sum();
+                    let defense_bonus: i32 = (&equipped, &defense_bonuses)
+                        .join()
+                        .filter(|(worn, _)| worn.owner == melee_intent.target)
+                        .map(|(_, bonus)| bonus.defense)
+                        .sum();
+                    let dice = (&equipped, &damage_dice)
+                        .join()
+                        .find(|(worn, _)| worn.owner == entity)
+                        .map_or(combat::UNARMED_DAMAGE, |(_, dice)| *dice);
+
+                    let outcome = combat::roll_attack(
+                        &mut rng,
+                        stats.power + power_bonus,
+                        combat::armor_class(target_stats.defense + defense_bonus),
+                    );
+
+                    match outcome {
+                        AttackOutcome::Fumble => log.entries.push(format!(
+                            "{} fumbles an attack on {}",
                             &name.name, &target_name.name
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {} for {} hp",
-                            &name.name, &target_name.name, damage
-                        ));
-                        Damage::new_damage(&mut damages, melee_intent.target, damage);
+                        )),
+                        AttackOutcome::Miss => log
+                            .entries
+                            .push(format!("{} misses {}", &name.name, &target_name.name)),
+                        AttackOutcome::Hit | AttackOutcome::Critical => {
+                            let damage = combat::roll_damage(
+                                &mut rng,
+                                dice,
+                                combat::damage_bonus(stats.power) + power_bonus,
+                                outcome,
+                            );
+
+                            if outcome == AttackOutcome::Critical {
+                                log.entries.push(format!(
+                                    "{} critically hits {} for {} hp!",
+                                    &name.name, &target_name.name, damage
+                                ));
+                            } else {
+                                log.entries.push(format!(
+                                    "{} hits {} for {} hp",
+                                    &name.name, &target_name.name, damage
+                                ));
+                            }
+
+                            Damage::new_damage(&mut damages, melee_intent.target, damage);
+                        }
                     }
                 }
             }
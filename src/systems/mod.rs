@@ -1,12 +1,28 @@
 pub mod damage;
 pub mod enemy_ai;
+pub mod gold;
 pub mod inventory;
+pub mod lighting;
 pub mod map_indexing;
 pub mod melee_combat;
+pub mod ranged_combat;
+pub mod status_effect;
+pub mod teleport;
+pub mod terrain;
+pub mod throw;
+pub mod trap;
 pub mod visibility;
 
 pub use damage::DamageSystem;
 pub use enemy_ai::EnemyAISystem;
+pub use gold::GoldSystem;
+pub use lighting::LightingSystem;
 pub use map_indexing::MapIndexingSystem;
 pub use melee_combat::MeleeCombatSystem;
+pub use ranged_combat::RangedCombatSystem;
+pub use status_effect::StatusEffectSystem;
+pub use teleport::TeleportSystem;
+pub use terrain::TerrainSystem;
+pub use throw::ThrowSystem;
+pub use trap::TrapSystem;
 pub use visibility::VisibilitySystem;
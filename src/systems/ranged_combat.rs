@@ -0,0 +1,245 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use crate::{
+    combat::{self, AttackOutcome},
+    components::{
+        Ammunition, Attributes, Charges, CombatStats, Cursed, Damage, DefenseBonus, DesiresFire,
+        Enemy, Equipped, InInventory, Name, Position, RangedWeapon, Stackable, Viewshed,
+    },
+    log::GameLog,
+    map::{CellType, Map},
+    systems::inventory::{merge_into_stack, ItemDetails},
+};
+
+/// Shoots ammunition from equipped ranged weapons. Whether a shot lands comes down to a d20 roll plus the
+/// shooter's dexterity against the target's armor class. Arrows and bolts that hit are spent, while misses fall
+/// somewhere near the target to be picked up again
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, RangedWeapon>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Charges>,
+        ReadStorage<'a, Cursed>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, DesiresFire>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InInventory>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, Stackable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            map,
+            names,
+            ranged_weapons,
+            equipped,
+            attributes,
+            combat_stats,
+            defense_bonuses,
+            charges,
+            cursed,
+            mut rng,
+            mut log,
+            mut desires_fire,
+            mut positions,
+            mut in_inventory,
+            mut damage,
+            mut stacks,
+        ) = data;
+
+        let shots: Vec<(Entity, Entity, Entity)> = (&entities, &desires_fire)
+            .join()
+            .map(|(shooter, intent)| (shooter, intent.ammo, intent.target))
+            .collect();
+
+        for (shooter, ammo, target) in shots {
+            let weapon = (&ranged_weapons, &equipped)
+                .join()
+                .find(|(_, worn)| worn.owner == shooter)
+                .map(|(weapon, _)| weapon);
+
+            let (weapon, stats, target_pos) =
+                match (weapon, combat_stats.get(target), positions.get(target)) {
+                    (Some(weapon), Some(stats), Some(pos)) => {
+                        (weapon, stats, Point::new(pos.x, pos.y))
+                    }
+                    _ => {
+                        // The shot never happened, so the arrow split off for it goes back in the quiver
+                        let details = ItemDetails {
+                            names: &names,
+                            charges: &charges,
+                            cursed: &cursed,
+                        };
+                        merge_into_stack(
+                            &entities,
+                            &details,
+                            &in_inventory,
+                            &mut stacks,
+                            ammo,
+                            shooter,
+                        );
+                        continue;
+                    }
+                };
+
+            in_inventory.remove(ammo);
+
+            let aim = attributes
+                .get(shooter)
+                .map_or(0, |attributes| attributes.dexterity_bonus());
+            let defense = stats.defense
+                + (&defense_bonuses, &equipped)
+                    .join()
+                    .filter(|(_, worn)| worn.owner == target)
+                    .map(|(bonus, _)| bonus.defense)
+                    .sum::<i32>();
+
+            let ammo_name = &names.get(ammo).unwrap().name;
+            let target_name = &names.get(target).unwrap().name;
+
+            let outcome = combat::roll_attack(&mut rng, aim, combat::armor_class(defense));
+
+            if matches!(outcome, AttackOutcome::Hit | AttackOutcome::Critical) {
+                let amount = combat::roll_damage(&mut rng, weapon.damage, aim, outcome);
+                Damage::new_damage(&mut damage, target, amount);
+
+                if shooter == *player {
+                    if outcome == AttackOutcome::Critical {
+                        log.entries.push(format!(
+                            "The {} strikes {} true for {} hp!",
+                            ammo_name, target_name, amount
+                        ));
+                    } else {
+                        log.entries.push(format!(
+                            "The {} hits {} for {} hp",
+                            ammo_name, target_name, amount
+                        ));
+                    }
+                }
+
+                entities.delete(ammo).expect("unable to delete spent ammo");
+                continue;
+            }
+
+            if shooter == *player {
+                if outcome == AttackOutcome::Fumble {
+                    log.entries
+                        .push(format!("You fumble the {} and it goes wide", ammo_name));
+                } else {
+                    log.entries
+                        .push(format!("The {} misses {}", ammo_name, target_name));
+                }
+            }
+
+            let landing = landing_near(&map, &mut rng, target_pos);
+            positions
+                .insert(
+                    ammo,
+                    Position {
+                        x: landing.x,
+                        y: landing.y,
+                    },
+                )
+                .expect("unable to insert missed ammo position");
+        }
+
+        desires_fire.clear();
+    }
+}
+
+/// Picks a cell on or next to the target for a missed shot to fall in, avoiding anywhere it would be lost
+fn landing_near(map: &Map, rng: &mut RandomNumberGenerator, target: Point) -> Point {
+    let mut cells: Vec<Point> = Vec::new();
+
+    for y in target.y - 1..=target.y + 1 {
+        for x in target.x - 1..=target.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let cell = map.cells[map.xy_idx(x, y)];
+            if cell.is_walkable()
+                && !matches!(
+                    cell,
+                    CellType::ClosedDoor | CellType::Lava | CellType::DeepWater
+                )
+            {
+                cells.push(Point::new(x, y));
+            }
+        }
+    }
+
+    rng.random_slice_entry(&cells).copied().unwrap_or(target)
+}
+
+/// The ranged weapon the shooter has equipped, if any
+pub fn equipped_ranged_weapon(ecs: &World, shooter: Entity) -> Option<RangedWeapon> {
+    let weapons = ecs.read_storage::<RangedWeapon>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    (&weapons, &equipped)
+        .join()
+        .find(|(_, worn)| worn.owner == shooter)
+        .map(|(weapon, _)| weapon.clone())
+}
+
+/// The carried ammunition the shooter's equipped ranged weapon takes, if it has any
+pub fn loaded_ammo(ecs: &World, shooter: Entity) -> Option<Entity> {
+    let weapon = equipped_ranged_weapon(ecs, shooter)?;
+    let entities = ecs.entities();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let in_inventory = ecs.read_storage::<InInventory>();
+
+    (&entities, &ammunition, &in_inventory)
+        .join()
+        .find(|(_, ammo, carried)| carried.owner == shooter && ammo.ammo == weapon.ammo)
+        .map(|(entity, _, _)| entity)
+}
+
+/// Every enemy the shooter can see within range of its equipped ranged weapon, nearest first
+pub fn fire_targets(ecs: &World, shooter: Entity) -> Vec<Entity> {
+    let weapon = match equipped_ranged_weapon(ecs, shooter) {
+        Some(weapon) => weapon,
+        None => return Vec::new(),
+    };
+
+    let entities = ecs.entities();
+    let enemies = ecs.read_storage::<Enemy>();
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    let (origin, viewshed) = match (positions.get(shooter), viewsheds.get(shooter)) {
+        (Some(pos), Some(viewshed)) => (Point::new(pos.x, pos.y), viewshed),
+        _ => return Vec::new(),
+    };
+
+    let mut targets: Vec<(Entity, f32)> = (&entities, &enemies, &positions)
+        .join()
+        .map(|(entity, _, pos)| (entity, Point::new(pos.x, pos.y)))
+        .filter(|(_, pos)| viewshed.visible_cells.contains(pos))
+        .map(|(entity, pos)| {
+            (
+                entity,
+                rltk::DistanceAlg::Pythagoras.distance2d(origin, pos),
+            )
+        })
+        .filter(|(_, distance)| *distance <= weapon.range as f32)
+        .collect();
+
+    targets.sort_by(|a, b| a.1.total_cmp(&b.1));
+    targets.into_iter().map(|(entity, _)| entity).collect()
+}
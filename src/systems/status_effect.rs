@@ -0,0 +1,67 @@
+use specs::prelude::*;
+
+use crate::{
+    components::{DetectsMonsters, StatusEffect, StatusEffectType},
+    log::GameLog,
+    state::RunState,
+};
+
+/// Counts down everyone's status effects and monster detection once a turn, removing those that have run out
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, StatusEffect>,
+        WriteStorage<'a, DetectsMonsters>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player, run_state, mut log, mut status_effects, mut detections) = data;
+
+        if *run_state != RunState::EnemyTurn {
+            return;
+        }
+
+        let mut expired: Vec<Entity> = Vec::new();
+        for (entity, effect) in (&entities, &mut status_effects).join() {
+            effect.turns -= 1;
+
+            if effect.turns < 1 {
+                expired.push(entity);
+            }
+        }
+
+        for entity in expired {
+            if let Some(effect) = status_effects.remove(entity) {
+                if entity == *player {
+                    log.entries.push(match effect.effect {
+                        StatusEffectType::Confusion => "You are no longer confused.".to_string(),
+                        StatusEffectType::Sleep => "You wake up.".to_string(),
+                    });
+                }
+            }
+        }
+
+        let mut faded: Vec<Entity> = Vec::new();
+        for (entity, detection) in (&entities, &mut detections).join() {
+            detection.turns -= 1;
+
+            if detection.turns < 1 {
+                faded.push(entity);
+            }
+        }
+
+        for entity in faded {
+            detections.remove(entity);
+
+            if entity == *player {
+                log.entries
+                    .push("You can no longer sense monsters.".to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use crate::{
+    components::{BlocksCell, DesiresTeleport, EntityMoved, Name, Position, Viewshed},
+    log::GameLog,
+    map::{CellType, Map},
+};
+
+// How many random cells are checked for somewhere to land before giving up
+const TELEPORT_TRIES: i32 = 100;
+
+/// Moves everything that wants to teleport, either to the cell it chose or anywhere on the level
+pub struct TeleportSystem {}
+
+impl<'a> System<'a> for TeleportSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, BlocksCell>,
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, DesiresTeleport>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, EntityMoved>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            names,
+            blockers,
+            mut map,
+            mut player_pos,
+            mut rng,
+            mut log,
+            mut desires_teleport,
+            mut positions,
+            mut viewsheds,
+            mut moved,
+        ) = data;
+
+        for (entity, intent) in (&entities, &desires_teleport).join() {
+            let is_player = entity == *player;
+
+            let dest_idx = match intent.destination {
+                Some(dest) => {
+                    let in_bounds =
+                        dest.x >= 0 && dest.x < map.width && dest.y >= 0 && dest.y < map.height;
+
+                    if in_bounds && is_open_cell(&map, map.xy_idx(dest.x, dest.y)) {
+                        Some(map.xy_idx(dest.x, dest.y))
+                    } else {
+                        None
+                    }
+                }
+                None => random_open_cell(&map, &mut rng),
+            };
+
+            let dest_idx = match dest_idx {
+                Some(idx) => idx,
+                None => {
+                    if is_player {
+                        log.entries.push("The magic fizzles.".to_string());
+                    }
+                    continue;
+                }
+            };
+
+            let pos = match positions.get_mut(entity) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let from_idx = map.xy_idx(pos.x, pos.y);
+            let seen_leaving = map.visible_cells[from_idx];
+
+            // Keep the map index current, so nothing else lands on the same cell this turn
+            map.cell_content[from_idx].retain(|other| *other != entity);
+            map.cell_content[dest_idx].push(entity);
+
+            if blockers.get(entity).is_some() {
+                map.blocked[from_idx] = false;
+                map.blocked[dest_idx] = true;
+            }
+
+            pos.x = dest_idx as i32 % map.width;
+            pos.y = dest_idx as i32 / map.width;
+
+            let name = names.get(entity).map_or("Something", |n| &n.name);
+            if is_player {
+                *player_pos = Point::new(pos.x, pos.y);
+                log.entries
+                    .push("You find yourself somewhere else!".to_string());
+            } else if seen_leaving {
+                log.entries.push(format!("{} vanishes!", name));
+            } else if map.visible_cells[dest_idx] {
+                log.entries
+                    .push(format!("{} appears out of thin air!", name));
+            }
+
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+
+            moved
+                .insert(entity, EntityMoved {})
+                .expect("unable to insert moved marker");
+        }
+
+        desires_teleport.clear();
+    }
+}
+
+/// Whether idx is somewhere safe to stand with nothing in the way: anything walkable but a closed door, lava or
+/// deep water
+pub fn is_open_cell(map: &Map, idx: usize) -> bool {
+    map.cells[idx].is_walkable()
+        && !matches!(
+            map.cells[idx],
+            CellType::ClosedDoor | CellType::Lava | CellType::DeepWater
+        )
+        && !map.blocked[idx]
+}
+
+/// Picks a random open cell on the map
+fn random_open_cell(map: &Map, rng: &mut RandomNumberGenerator) -> Option<usize> {
+    for _ in 0..TELEPORT_TRIES {
+        let idx = rng.range(0, map.cells.len());
+
+        if is_open_cell(map, idx) {
+            return Some(idx);
+        }
+    }
+
+    None
+}
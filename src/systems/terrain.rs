@@ -0,0 +1,99 @@
+use specs::prelude::*;
+
+use crate::{
+    components::{Damage, EntityMoved, Equipped, InInventory, Name, Position, Weight},
+    identification::Identification,
+    log::GameLog,
+    map::{CellType, Map},
+};
+
+// How much stepping into lava hurts
+const LAVA_DAMAGE: i32 = 10;
+
+// Items at least this heavy are lost by anyone who swims through deep water carrying them, unless they're
+// held or worn
+const HEAVY_ITEM_WEIGHT: i32 = 5;
+
+pub struct TerrainSystem {}
+
+impl<'a> System<'a> for TerrainSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Identification>,
+        ReadStorage<'a, EntityMoved>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Equipped>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, InInventory>,
+        WriteStorage<'a, Damage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            map,
+            identification,
+            moved,
+            names,
+            weights,
+            equipped,
+            mut log,
+            positions,
+            in_inventory,
+            mut damage,
+        ) = data;
+
+        let mut sunk: Vec<(Entity, Entity)> = Vec::new();
+
+        for (entity, _moved, pos) in (&entities, &moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            let is_player = entity == *player;
+            let name = names.get(entity).map_or("Something", |n| &n.name);
+
+            match map.cells[idx] {
+                CellType::Lava => {
+                    Damage::new_damage(&mut damage, entity, LAVA_DAMAGE);
+
+                    if is_player {
+                        log.entries
+                            .push(format!("The lava burns you for {} hp!", LAVA_DAMAGE));
+                    } else if map.visible_cells[idx] {
+                        log.entries.push(format!("{} is burned by the lava", name));
+                    }
+                }
+
+                CellType::DeepWater => {
+                    for (item, carried, weight, _) in
+                        (&entities, &in_inventory, &weights, !&equipped).join()
+                    {
+                        if carried.owner == entity && weight.weight >= HEAVY_ITEM_WEIGHT {
+                            sunk.push((item, entity));
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        // Like anything thrown into deep water, they're gone for good
+        for (item, owner) in sunk {
+            if owner == *player {
+                let item_name = names
+                    .get(item)
+                    .map_or("something".to_string(), |n| identification.name_of(n));
+                log.entries.push(format!(
+                    "Your {} slips away and sinks out of sight",
+                    item_name
+                ));
+            }
+
+            entities.delete(item).expect("unable to delete sunk item");
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use rltk::Point;
+use specs::prelude::*;
+
+use crate::{
+    components::{
+        CombatStats, Cursed, Damage, DesiresThrowItem, DetectsMonsters, Equipped, InInventory,
+        MeleePowerBonus, Name, Position, ProvidesHealing, Shatters, StatusEffect, Weight,
+    },
+    identification::Identification,
+    log::GameLog,
+    map::{CellType, Map},
+};
+
+// How many cells away anything can be thrown
+pub const THROW_RANGE: i32 = 6;
+
+// How far around where it lands a shattered item splashes
+const SHATTER_RADIUS: i32 = 1;
+
+/// Sends thrown items flying towards their targets. They stop at the first wall or creature in the way, where
+/// they either shatter over everything nearby or hit what they struck and fall to the ground
+pub struct ThrowSystem {}
+
+impl<'a> System<'a> for ThrowSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Identification>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, Shatters>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, DesiresThrowItem>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InInventory>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Cursed>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, StatusEffect>,
+        WriteStorage<'a, DetectsMonsters>,
+        WriteStorage<'a, Damage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            map,
+            identification,
+            names,
+            weights,
+            power_bonuses,
+            heals,
+            shatters,
+            mut log,
+            mut desires_throw,
+            mut positions,
+            mut in_inventory,
+            mut equipped,
+            mut curses,
+            mut combat_stats,
+            mut status_effects,
+            mut detections,
+            mut damage,
+        ) = data;
+
+        let throws: Vec<(Entity, Entity, Point, Point)> = (&entities, &desires_throw, &positions)
+            .join()
+            .map(|(thrower, intent, pos)| {
+                (
+                    thrower,
+                    intent.item,
+                    Point::new(pos.x, pos.y),
+                    intent.target,
+                )
+            })
+            .collect();
+
+        for (thrower, item, from, target) in throws {
+            let item_name = identification.name_of(names.get(item).unwrap());
+
+            if equipped.get(item).is_some() {
+                if let Some(curse) = curses.get_mut(item) {
+                    curse.known = true;
+
+                    if thrower == *player {
+                        log.entries.push(format!(
+                            "You can't let go of the {}, it's cursed!",
+                            item_name
+                        ));
+                    }
+                    continue;
+                }
+
+                equipped.remove(item);
+            }
+
+            in_inventory.remove(item);
+
+            let (landing, struck) = flight_path(&map, &combat_stats, from, target);
+
+            if shatters.get(item).is_some() {
+                if thrower == *player {
+                    log.entries.push(format!("The {} shatters!", item_name));
+                }
+
+                let splashed: Vec<Entity> = (&entities, &positions, &combat_stats)
+                    .join()
+                    .filter(|(_, pos, _)| {
+                        rltk::DistanceAlg::Chebyshev.distance2d(landing, Point::new(pos.x, pos.y))
+                            <= SHATTER_RADIUS as f32
+                    })
+                    .map(|(entity, _, _)| entity)
+                    .collect();
+
+                let effect = status_effects.get(item).cloned();
+                let detection = detections.get(item).cloned();
+
+                for victim in splashed {
+                    if let Some(healer) = heals.get(item) {
+                        let stats = combat_stats.get_mut(victim).unwrap();
+                        stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
+                    }
+
+                    if let Some(effect) = effect.as_ref() {
+                        status_effects
+                            .insert(victim, effect.clone())
+                            .expect("unable to insert splashed effect");
+                    }
+
+                    if let Some(detection) = detection.as_ref() {
+                        detections
+                            .insert(victim, detection.clone())
+                            .expect("unable to insert splashed detection");
+                    }
+
+                    if victim == *player {
+                        log.entries.push("You are caught in the splash".to_string());
+                    } else if thrower == *player {
+                        let victim_name = names.get(victim).map_or("Something", |n| &n.name);
+
+                        log.entries
+                            .push(format!("{} is caught in the splash", victim_name));
+                    }
+                }
+
+                entities
+                    .delete(item)
+                    .expect("unable to delete shattered item");
+                continue;
+            }
+
+            if let Some(victim) = struck {
+                let heft = weights.get(item).map_or(0, |weight| weight.weight);
+                let edge = power_bonuses.get(item).map_or(0, |bonus| bonus.power);
+                let amount = i32::max(1, heft / 2 + i32::max(0, edge));
+
+                Damage::new_damage(&mut damage, victim, amount);
+
+                if thrower == *player {
+                    let victim_name = names.get(victim).map_or("something", |n| &n.name);
+
+                    log.entries.push(format!(
+                        "The {} hits {} for {} hp",
+                        item_name, victim_name, amount
+                    ));
+                }
+            }
+
+            // Anything coming down in lava or deep water is gone for good
+            let landing_idx = map.xy_idx(landing.x, landing.y);
+            if matches!(map.cells[landing_idx], CellType::Lava | CellType::DeepWater) {
+                if thrower == *player {
+                    log.entries
+                        .push(format!("The {} sinks out of sight", item_name));
+                }
+
+                entities.delete(item).expect("unable to delete sunk item");
+                continue;
+            }
+
+            positions
+                .insert(
+                    item,
+                    Position {
+                        x: landing.x,
+                        y: landing.y,
+                    },
+                )
+                .expect("unable to insert landing position");
+        }
+
+        desires_throw.clear();
+    }
+}
+
+/// Follows a line from one cell towards another, returning where a thrown item comes down and any creature it
+/// struck on the way. Walls and closed doors stop it short
+fn flight_path(
+    map: &Map,
+    combat_stats: &WriteStorage<CombatStats>,
+    from: Point,
+    target: Point,
+) -> (Point, Option<Entity>) {
+    let mut landing = from;
+
+    for cell in rltk::line2d_bresenham(from, target)
+        .into_iter()
+        .filter(|cell| *cell != from)
+    {
+        let idx = map.xy_idx(cell.x, cell.y);
+
+        if !map.cells[idx].is_walkable() || map.cells[idx] == CellType::ClosedDoor {
+            break;
+        }
+
+        landing = cell;
+
+        let struck = map.cell_content[idx]
+            .iter()
+            .find(|entity| combat_stats.get(**entity).is_some());
+
+        if let Some(victim) = struck {
+            return (landing, Some(*victim));
+        }
+    }
+
+    (landing, None)
+}
@@ -0,0 +1,146 @@
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+use crate::{
+    components::{
+        Alerted, Damage, DesiresTeleport, Enemy, EntityMoved, Hidden, Name, Position, Trap,
+        TrapType,
+    },
+    log::GameLog,
+    map::Map,
+    state::RunState,
+};
+
+pub struct TrapSystem {}
+
+impl<'a> System<'a> for TrapSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Trap>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, RunState>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Hidden>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, Alerted>,
+        WriteStorage<'a, DesiresTeleport>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            map,
+            traps,
+            names,
+            enemies,
+            positions,
+            mut run_state,
+            mut rng,
+            mut log,
+            mut moved,
+            mut hidden,
+            mut damage,
+            mut alerted,
+            mut desires_teleport,
+        ) = data;
+
+        // Find every trap sharing a cell with an entity that just moved
+        let mut triggered: Vec<(Entity, Entity, TrapType, usize)> = Vec::new();
+        for (entity, _moved, pos) in (&entities, &moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+
+            for other in map.cell_content[idx].iter() {
+                if let Some(trap) = traps.get(*other) {
+                    triggered.push((entity, *other, trap.kind, idx));
+                }
+            }
+        }
+
+        moved.clear();
+
+        for (victim, trap, kind, idx) in triggered {
+            let is_player = victim == *player;
+            let witnessed = is_player || map.visible_cells[idx];
+
+            // A trap that has gone off is no longer a secret
+            hidden.remove(trap);
+
+            let trap_name = names.get(trap).map_or("trap", |n| &n.name);
+            let victim_name = names.get(victim).map_or("Something", |n| &n.name);
+
+            if is_player {
+                log.entries.push(format!("You trigger a {}!", trap_name));
+            } else if witnessed {
+                log.entries
+                    .push(format!("{} triggers a {}!", victim_name, trap_name));
+            }
+
+            match kind {
+                TrapType::Spike => {
+                    let amount = rng.roll_dice(1, 6);
+                    Damage::new_damage(&mut damage, victim, amount);
+
+                    if witnessed {
+                        log.entries
+                            .push(format!("Spikes impale {} for {} hp", victim_name, amount));
+                    }
+                }
+
+                TrapType::PoisonDart => {
+                    let amount = rng.roll_dice(2, 4) + map.depth;
+                    Damage::new_damage(&mut damage, victim, amount);
+
+                    if witnessed {
+                        log.entries.push(format!(
+                            "A poisoned dart hits {} for {} hp",
+                            victim_name, amount
+                        ));
+                    }
+                }
+
+                TrapType::Teleport => {
+                    desires_teleport
+                        .insert(victim, DesiresTeleport { destination: None })
+                        .expect("unable to insert teleport");
+                }
+
+                TrapType::Alarm => {
+                    for (entity, _enemy) in (&entities, &enemies).join() {
+                        alerted
+                            .insert(entity, Alerted {})
+                            .expect("unable to insert alert");
+                    }
+
+                    log.entries
+                        .push("An alarm blares throughout the level!".to_string());
+
+                    // Alarms only go off once
+                    entities.delete(trap).expect("unable to delete alarm");
+                }
+
+                TrapType::Trapdoor => {
+                    if is_player {
+                        log.entries.push("You fall through a trapdoor!".to_string());
+                        *run_state = RunState::NextLevel;
+                    } else {
+                        if witnessed {
+                            log.entries
+                                .push(format!("{} falls through a trapdoor!", victim_name));
+                        }
+
+                        entities
+                            .delete(victim)
+                            .expect("unable to delete fallen entity");
+                    }
+                }
+            }
+        }
+    }
+}
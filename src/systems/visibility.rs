@@ -1,11 +1,18 @@
-use rltk::{field_of_view, Point};
+use rltk::{field_of_view, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
 use crate::{
-    components::{Player, Position, Viewshed},
+    components::{Hidden, Name, Player, Position, Viewshed},
+    log::GameLog,
     map::Map,
 };
 
+// The player can make out cells this close even in pitch darkness
+const DARK_SIGHT_RANGE: f32 = 1.5;
+
+// The player has a 1 in this chance of spotting each hidden thing in view when they look around
+const SPOT_HIDDEN_CHANCE: i32 = 24;
+
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem {
@@ -15,10 +22,24 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteExpect<'a, Map>,
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Hidden>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, player, mut map, mut viewsheds, positions) = data;
+        let (
+            entities,
+            player,
+            mut map,
+            mut viewsheds,
+            positions,
+            names,
+            mut hidden,
+            mut rng,
+            mut log,
+        ) = data;
 
         for (entitites, viewshed, pos) in (&entities, &mut viewsheds, &positions).join() {
             if viewshed.dirty {
@@ -33,6 +54,14 @@ impl<'a> System<'a> for VisibilitySystem {
 
                 let maybe_player = player.get(entitites);
                 if let Some(_player) = maybe_player {
+                    // Beyond arm's reach the player only sees what's lit
+                    let origin = Point::new(pos.x, pos.y);
+                    viewshed.visible_cells.retain(|p| {
+                        map.is_lit(map.xy_idx(p.x, p.y))
+                            || rltk::DistanceAlg::Pythagoras.distance2d(origin, *p)
+                                <= DARK_SIGHT_RANGE
+                    });
+
                     for cell in map.visible_cells.iter_mut() {
                         *cell = false
                     }
@@ -42,6 +71,18 @@ impl<'a> System<'a> for VisibilitySystem {
 
                         map.revealed_cells[idx] = true;
                         map.visible_cells[idx] = true;
+
+                        // Perception check for anything hidden in view
+                        for entity in map.cell_content[idx].iter() {
+                            if hidden.get(*entity).is_some()
+                                && rng.roll_dice(1, SPOT_HIDDEN_CHANCE) == 1
+                            {
+                                if let Some(name) = names.get(*entity) {
+                                    log.entries.push(format!("You spot a {}.", name.name));
+                                }
+                                hidden.remove(*entity);
+                            }
+                        }
                     }
                 }
             }
@@ -1,9 +1,10 @@
 use specs::prelude::*;
 
 use crate::{
-    components::{CombatStats, Player},
+    components::{CombatStats, Player, Purse},
     log,
     map::Map,
+    save::SaveMetadata,
     ui::tooltip::draw_tooltips,
 };
 
@@ -27,8 +28,38 @@ pub fn draw_ui(ecs: &World, ctx: &mut rltk::Rltk) {
         &depth,
     );
 
+    let metadata = ecs.fetch::<SaveMetadata>();
+    if metadata.tampered {
+        ctx.print_color(
+            2,
+            49,
+            rltk::RGB::named(rltk::RED),
+            rltk::RGB::named(rltk::BLACK),
+            "TAMPERED",
+        );
+    } else if metadata.ironman {
+        ctx.print_color(
+            2,
+            49,
+            rltk::RGB::named(rltk::YELLOW),
+            rltk::RGB::named(rltk::BLACK),
+            "Ironman",
+        );
+    }
+
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
+    let purses = ecs.read_storage::<Purse>();
+
+    for (_player, purse) in (&players, &purses).join() {
+        ctx.print_color(
+            66,
+            49,
+            rltk::RGB::named(rltk::GOLD),
+            rltk::RGB::named(rltk::BLACK),
+            format!("Gold: {}", purse.gold),
+        );
+    }
 
     for (_player, stats) in (&players, &combat_stats).join() {
         let health = format!("HP: {}/{}", stats.hp, stats.max_hp);
@@ -51,17 +82,13 @@ pub fn draw_ui(ecs: &World, ctx: &mut rltk::Rltk) {
 
         let log = ecs.fetch::<log::GameLog>();
 
-        let mut y = 44;
-        for s in log.entries.iter().rev() {
-            if y < 49 {
-                ctx.print(2, y, s);
-            }
-            y += 1;
+        for (y, s) in (44..49).zip(log.entries.iter().rev()) {
+            ctx.print(2, y, s);
         }
     }
 
     let mouse_pos = ctx.mouse_pos();
     ctx.set_bg(mouse_pos.0, mouse_pos.1, rltk::RGB::named(rltk::MAGENTA));
 
-    draw_tooltips(&ecs, ctx);
+    draw_tooltips(ecs, ctx);
 }
@@ -2,10 +2,14 @@ use specs::prelude::*;
 use specs::Entity;
 
 use crate::{
-    components::{InInventory, Name},
+    components::{Attributes, Charges, Cursed, Equipped, InInventory, Name, Stackable, Weight},
+    identification::Identification,
     state::State,
 };
 
+// The most items a menu lists; as many as fit on the screen
+pub(crate) const MAX_LISTED: usize = 44;
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum ItemMenuResult {
     Cancel,
@@ -13,6 +17,77 @@ pub enum ItemMenuResult {
     Selected,
 }
 
+/// The letter the item at the given index is listed under: a to z, then A to Z
+pub(crate) fn item_letter(index: usize) -> rltk::FontCharType {
+    if index < 26 {
+        rltk::to_cp437('a') + index as rltk::FontCharType
+    } else {
+        rltk::to_cp437('A') + (index - 26) as rltk::FontCharType
+    }
+}
+
+/// The index of the item listed under the pressed letter, shift picking out the capitals
+pub(crate) fn selected_index(ctx: &rltk::Rltk, key: rltk::VirtualKeyCode) -> Option<usize> {
+    let letter = rltk::letter_to_option(key);
+
+    if letter < 0 {
+        None
+    } else if ctx.shift {
+        Some(letter as usize + 26)
+    } else {
+        Some(letter as usize)
+    }
+}
+
+/// An item's name as the player knows it, followed by how many there are, its charges and whether it's equipped or known to be
+/// cursed
+pub(crate) fn item_label(ecs: &World, item: Entity, name: &Name) -> String {
+    let mut label = ecs.fetch::<Identification>().name_of(name);
+
+    if let Some(stack) = ecs.read_storage::<Stackable>().get(item) {
+        if stack.quantity > 1 {
+            label.push_str(&format!(" (x{})", stack.quantity));
+        }
+    }
+
+    if let Some(charge) = ecs.read_storage::<Charges>().get(item) {
+        label.push_str(&format!(" ({}/{})", charge.current, charge.max));
+    }
+
+    if ecs.read_storage::<Equipped>().get(item).is_some() {
+        label.push_str(" (equipped)");
+    }
+
+    if ecs
+        .read_storage::<Cursed>()
+        .get(item)
+        .is_some_and(|curse| curse.known)
+    {
+        label.push_str(" (cursed)");
+    }
+
+    label
+}
+
+/// The total weight the entity carries, out of what it can carry if it has a limit
+fn carried_weight_label(ecs: &World, owner: Entity) -> String {
+    let entities = ecs.entities();
+    let inventory = ecs.read_storage::<InInventory>();
+    let weights = ecs.read_storage::<Weight>();
+    let stacks = ecs.read_storage::<Stackable>();
+
+    let carried: i32 = (&entities, &inventory, &weights)
+        .join()
+        .filter(|(_, carried, _)| carried.owner == owner)
+        .map(|(item, _, weight)| weight.of_stack(stacks.get(item)))
+        .sum();
+
+    match ecs.read_storage::<Attributes>().get(owner) {
+        Some(attributes) => format!("Weight {}/{}", carried, attributes.carrying_capacity()),
+        None => format!("Weight {}", carried),
+    }
+}
+
 pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
@@ -22,13 +97,13 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
     let inventory = (&inventory_list, &names)
         .join()
         .filter(|item| item.0.owner == *player);
-    let count = inventory.count();
+    let count = usize::min(inventory.count(), MAX_LISTED);
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
-        31,
+        44,
         (count + 3) as i32,
         rltk::RGB::named(rltk::WHITE),
         rltk::RGB::named(rltk::BLACK),
@@ -40,6 +115,13 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
         rltk::RGB::named(rltk::BLACK),
         "Inventory",
     );
+    ctx.print_color(
+        40,
+        y - 2,
+        rltk::RGB::named(rltk::YELLOW),
+        rltk::RGB::named(rltk::BLACK),
+        carried_weight_label(&gs.ecs, *player),
+    );
     ctx.print_color(
         18,
         y + count as i32 + 1,
@@ -50,11 +132,14 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
 
     let mut equippable: Vec<Entity> = Vec::new();
 
-    let mut j = 0;
-    for (entity, _inventory, name) in (&entities, &inventory_list, &names)
+    for (j, (entity, _inventory, name)) in (&entities, &inventory_list, &names)
         .join()
         .filter(|item| item.1.owner == *player)
+        .take(MAX_LISTED)
+        .enumerate()
     {
+        let y = y + j as i32;
+
         ctx.set(
             17,
             y,
@@ -67,7 +152,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
             y,
             rltk::RGB::named(rltk::YELLOW),
             rltk::RGB::named(rltk::BLACK),
-            97 + j as rltk::FontCharType,
+            item_letter(j),
         );
         ctx.set(
             19,
@@ -77,9 +162,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
-        y += 1;
-        j += 1;
+        ctx.print(21, y, item_label(&gs.ecs, entity, name));
 
         equippable.push(entity);
     }
@@ -88,21 +171,30 @@ pub fn show_inventory(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
         None => (ItemMenuResult::NoResponse, None),
         Some(key) => match key {
             rltk::VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-            _ => {
-                let selection = rltk::letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+            _ => match selected_index(ctx, key) {
+                Some(selection) if selection < count => {
+                    (ItemMenuResult::Selected, Some(equippable[selection]))
                 }
-                (ItemMenuResult::NoResponse, None)
-            }
+                _ => (ItemMenuResult::NoResponse, None),
+            },
         },
     }
 }
 
 pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult, Option<Entity>) {
+    pick_item_menu(gs, ctx, "Drop Which Item?")
+}
+
+pub fn throw_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult, Option<Entity>) {
+    pick_item_menu(gs, ctx, "Throw Which Item?")
+}
+
+/// Lists the player's items under the given title for them to pick one
+fn pick_item_menu(
+    gs: &mut State,
+    ctx: &mut rltk::Rltk,
+    title: &str,
+) -> (ItemMenuResult, Option<Entity>) {
     let player = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let inventory_list = gs.ecs.read_storage::<InInventory>();
@@ -111,13 +203,13 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
     let inventory = (&inventory_list, &names)
         .join()
         .filter(|item| item.0.owner == *player);
-    let count = inventory.count();
+    let count = usize::min(inventory.count(), MAX_LISTED);
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
-        31,
+        44,
         (count + 3) as i32,
         rltk::RGB::named(rltk::WHITE),
         rltk::RGB::named(rltk::BLACK),
@@ -127,7 +219,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
         y - 2,
         rltk::RGB::named(rltk::YELLOW),
         rltk::RGB::named(rltk::BLACK),
-        "Drop Which Item?",
+        title,
     );
     ctx.print_color(
         18,
@@ -138,11 +230,14 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _inventory, name) in (&entities, &inventory_list, &names)
+    for (j, (entity, _inventory, name)) in (&entities, &inventory_list, &names)
         .join()
         .filter(|item| item.1.owner == *player)
+        .take(MAX_LISTED)
+        .enumerate()
     {
+        let y = y + j as i32;
+
         ctx.set(
             17,
             y,
@@ -155,7 +250,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
             y,
             rltk::RGB::named(rltk::YELLOW),
             rltk::RGB::named(rltk::BLACK),
-            97 + j as rltk::FontCharType,
+            item_letter(j),
         );
         ctx.set(
             19,
@@ -165,26 +260,20 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> (ItemMenuResult,
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, item_label(&gs.ecs, entity, name));
         equippable.push(entity);
-        y += 1;
-        j += 1;
     }
 
     match ctx.key {
         None => (ItemMenuResult::NoResponse, None),
         Some(key) => match key {
             rltk::VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-            _ => {
-                let selection = rltk::letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+            _ => match selected_index(ctx, key) {
+                Some(selection) if selection < count => {
+                    (ItemMenuResult::Selected, Some(equippable[selection]))
                 }
-                (ItemMenuResult::NoResponse, None)
-            }
+                _ => (ItemMenuResult::NoResponse, None),
+            },
         },
     }
 }
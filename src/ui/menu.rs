@@ -1,4 +1,7 @@
+use specs::prelude::*;
+
 use crate::{
+    log::GameLog,
     save,
     state::{RunState, State},
 };
@@ -6,6 +9,7 @@ use crate::{
 #[derive(PartialEq, Clone, Copy)]
 pub enum MainMenuSelection {
     NewGame,
+    NewIronmanGame,
     LoadGame,
     Quit,
 }
@@ -47,17 +51,33 @@ pub fn main_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> MainMenuResult {
             );
         }
 
+        if selection == MainMenuSelection::NewIronmanGame {
+            ctx.print_color_centered(
+                25,
+                rltk::RGB::named(rltk::MAGENTA),
+                rltk::RGB::named(rltk::BLACK),
+                "Begin Ironman Game",
+            );
+        } else {
+            ctx.print_color_centered(
+                25,
+                rltk::RGB::named(rltk::WHITE),
+                rltk::RGB::named(rltk::BLACK),
+                "Begin Ironman Game",
+            );
+        }
+
         if save_exists {
             if selection == MainMenuSelection::LoadGame {
                 ctx.print_color_centered(
-                    25,
+                    26,
                     rltk::RGB::named(rltk::MAGENTA),
                     rltk::RGB::named(rltk::BLACK),
                     "Load Game",
                 );
             } else {
                 ctx.print_color_centered(
-                    25,
+                    26,
                     rltk::RGB::named(rltk::WHITE),
                     rltk::RGB::named(rltk::BLACK),
                     "Load Game",
@@ -67,20 +87,33 @@ pub fn main_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> MainMenuResult {
 
         if selection == MainMenuSelection::Quit {
             ctx.print_color_centered(
-                26,
+                27,
                 rltk::RGB::named(rltk::MAGENTA),
                 rltk::RGB::named(rltk::BLACK),
                 "Quit",
             );
         } else {
             ctx.print_color_centered(
-                26,
+                27,
                 rltk::RGB::named(rltk::WHITE),
                 rltk::RGB::named(rltk::BLACK),
                 "Quit",
             );
         }
 
+        // With no game loaded, e.g. after a save failed to load, the log only holds the reason why
+        let player = *gs.ecs.fetch::<Entity>();
+        if !gs.ecs.is_alive(player) {
+            if let Some(entry) = gs.ecs.fetch::<GameLog>().entries.last() {
+                ctx.print_color_centered(
+                    30,
+                    rltk::RGB::named(rltk::RED),
+                    rltk::RGB::named(rltk::BLACK),
+                    entry,
+                );
+            }
+        }
+
         match ctx.key {
             None => {
                 return MainMenuResult::NoSelection {
@@ -98,12 +131,17 @@ pub fn main_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> MainMenuResult {
                     let mut next_selection;
                     match selection {
                         MainMenuSelection::NewGame => next_selection = MainMenuSelection::Quit,
-                        MainMenuSelection::LoadGame => next_selection = MainMenuSelection::NewGame,
+                        MainMenuSelection::NewIronmanGame => {
+                            next_selection = MainMenuSelection::NewGame
+                        }
+                        MainMenuSelection::LoadGame => {
+                            next_selection = MainMenuSelection::NewIronmanGame
+                        }
                         MainMenuSelection::Quit => next_selection = MainMenuSelection::LoadGame,
                     }
 
                     if next_selection == MainMenuSelection::LoadGame && !save_exists {
-                        next_selection = MainMenuSelection::NewGame;
+                        next_selection = MainMenuSelection::NewIronmanGame;
                     }
 
                     return MainMenuResult::NoSelection {
@@ -114,7 +152,12 @@ pub fn main_menu(gs: &mut State, ctx: &mut rltk::Rltk) -> MainMenuResult {
                 rltk::VirtualKeyCode::Down => {
                     let mut next_selection;
                     match selection {
-                        MainMenuSelection::NewGame => next_selection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::NewGame => {
+                            next_selection = MainMenuSelection::NewIronmanGame
+                        }
+                        MainMenuSelection::NewIronmanGame => {
+                            next_selection = MainMenuSelection::LoadGame
+                        }
                         MainMenuSelection::LoadGame => next_selection = MainMenuSelection::Quit,
                         MainMenuSelection::Quit => next_selection = MainMenuSelection::NewGame,
                     }
@@ -1,11 +1,13 @@
 pub mod hud;
 pub mod inventory;
 pub mod menu;
+pub mod shop;
 pub mod targeting;
 pub mod tooltip;
 
 pub use hud::*;
 pub use inventory::*;
 pub use menu::*;
+pub use shop::*;
 pub use targeting::*;
 pub use tooltip::*;
@@ -0,0 +1,144 @@
+use specs::prelude::*;
+use specs::Entity;
+
+use crate::{
+    components::{InInventory, Name, Purse, Value},
+    shop,
+    state::State,
+    ui::inventory::{item_label, item_letter, selected_index, MAX_LISTED},
+};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopMode {
+    Buy,
+    Sell,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopMenuResult {
+    Cancel,
+    NoResponse,
+    SwitchMode,
+    Selected,
+}
+
+/// Lists the shopkeeper's stock to buy from, or the player's belongings to sell, with their prices
+pub fn shop_menu(
+    gs: &mut State,
+    ctx: &mut rltk::Rltk,
+    shopkeeper: Entity,
+    mode: ShopMode,
+) -> (ShopMenuResult, Option<Entity>) {
+    let player = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let values = gs.ecs.read_storage::<Value>();
+    let inventory_list = gs.ecs.read_storage::<InInventory>();
+    let entities = gs.ecs.entities();
+
+    let (owner, title) = match mode {
+        ShopMode::Buy => (shopkeeper, "Buy (TAB to sell)"),
+        ShopMode::Sell => (*player, "Sell (TAB to buy)"),
+    };
+
+    let listed: Vec<(Entity, &Name, i32)> = (&entities, &inventory_list, &names, &values)
+        .join()
+        .filter(|item| item.1.owner == owner)
+        .filter(|item| mode == ShopMode::Buy || shop::can_sell(&gs.ecs, item.0))
+        .map(|(entity, _, name, value)| {
+            let price = match mode {
+                ShopMode::Buy => shop::buy_price(value),
+                ShopMode::Sell => shop::sell_price(value),
+            };
+
+            (entity, name, price)
+        })
+        .take(MAX_LISTED)
+        .collect();
+    let count = listed.len();
+
+    let gold = gs
+        .ecs
+        .read_storage::<Purse>()
+        .get(*player)
+        .map_or(0, |purse| purse.gold);
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        (count + 3) as i32,
+        rltk::RGB::named(rltk::WHITE),
+        rltk::RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        rltk::RGB::named(rltk::YELLOW),
+        rltk::RGB::named(rltk::BLACK),
+        title,
+    );
+    ctx.print_color(
+        50,
+        y - 2,
+        rltk::RGB::named(rltk::GOLD),
+        rltk::RGB::named(rltk::BLACK),
+        format!("Gold {}", gold),
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        rltk::RGB::named(rltk::YELLOW),
+        rltk::RGB::named(rltk::BLACK),
+        "ESCAPE to leave",
+    );
+
+    for (j, (entity, name, price)) in listed.iter().enumerate() {
+        let y = y + j as i32;
+
+        ctx.set(
+            17,
+            y,
+            rltk::RGB::named(rltk::WHITE),
+            rltk::RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            rltk::RGB::named(rltk::YELLOW),
+            rltk::RGB::named(rltk::BLACK),
+            item_letter(j),
+        );
+        ctx.set(
+            19,
+            y,
+            rltk::RGB::named(rltk::WHITE),
+            rltk::RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+        ctx.print_color(
+            58,
+            y,
+            rltk::RGB::named(rltk::GOLD),
+            rltk::RGB::named(rltk::BLACK),
+            format!("{} gold", price),
+        );
+    }
+
+    match ctx.key {
+        None => (ShopMenuResult::NoResponse, None),
+        Some(key) => match key {
+            rltk::VirtualKeyCode::Escape => (ShopMenuResult::Cancel, None),
+            rltk::VirtualKeyCode::Tab => (ShopMenuResult::SwitchMode, None),
+            _ => match selected_index(ctx, key) {
+                Some(selection) if selection < count => {
+                    (ShopMenuResult::Selected, Some(listed[selection].0))
+                }
+                _ => (ShopMenuResult::NoResponse, None),
+            },
+        },
+    }
+}
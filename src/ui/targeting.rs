@@ -1,7 +1,11 @@
 use specs::prelude::*;
 use specs::Entity;
 
-use crate::{components::Viewshed, state::State};
+use crate::{
+    camera,
+    components::{Name, Position, Viewshed},
+    state::State,
+};
 
 use super::inventory::ItemMenuResult;
 
@@ -34,7 +38,9 @@ pub fn ranged_target(
             // If the distance is within the item range...
             if distance <= range as f32 {
                 // Highlight
-                ctx.set_bg(idx.x, idx.y, rltk::RGB::named(rltk::BLUE));
+                if let Some(screen) = camera::world_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen.x, screen.y, rltk::RGB::named(rltk::BLUE));
+                }
                 available_cells.push(idx);
             }
         }
@@ -44,12 +50,13 @@ pub fn ranged_target(
 
     // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
+    let mouse_world = camera::screen_to_world(&gs.ecs, mouse_pos);
     let mut valid_target = false;
 
     // For each cell in range...
     for idx in available_cells.iter() {
         // If the mouse is hovering on that cell, it's targeted
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
+        if idx.x == mouse_world.x && idx.y == mouse_world.y {
             valid_target = true;
         }
     }
@@ -58,10 +65,7 @@ pub fn ranged_target(
         ctx.set_bg(mouse_pos.0, mouse_pos.1, rltk::RGB::named(rltk::CYAN));
         // If clicking on the targeted, valid cell return the coords
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(rltk::Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, Some(mouse_world));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, rltk::RGB::named(rltk::RED));
@@ -72,3 +76,46 @@ pub fn ranged_target(
 
     (ItemMenuResult::NoResponse, None)
 }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum FireTargetResult {
+    Cancel,
+    NoResponse,
+    NextTarget,
+    Fire,
+}
+
+/// Highlights what the player's ranged weapon is aimed at, letting them fire or cycle to the next target
+pub fn fire_target(gs: &mut State, ctx: &mut rltk::Rltk, target: Entity) -> FireTargetResult {
+    let positions = gs.ecs.read_storage::<Position>();
+    let names = gs.ecs.read_storage::<Name>();
+
+    let target_name = names.get(target).map_or("", |name| name.name.as_str());
+
+    ctx.print_color(
+        5,
+        0,
+        rltk::RGB::named(rltk::YELLOW),
+        rltk::RGB::named(rltk::BLACK),
+        format!(
+            "Fire at {} (TAB next target, F or ENTER fire, ESCAPE cancel)",
+            target_name
+        ),
+    );
+
+    if let Some(pos) = positions.get(target) {
+        if let Some(screen) = camera::world_to_screen(&gs.ecs, rltk::Point::new(pos.x, pos.y)) {
+            ctx.set_bg(screen.x, screen.y, rltk::RGB::named(rltk::RED));
+        }
+    }
+
+    match ctx.key {
+        None => FireTargetResult::NoResponse,
+        Some(key) => match key {
+            rltk::VirtualKeyCode::Escape => FireTargetResult::Cancel,
+            rltk::VirtualKeyCode::Tab => FireTargetResult::NextTarget,
+            rltk::VirtualKeyCode::F | rltk::VirtualKeyCode::Return => FireTargetResult::Fire,
+            _ => FireTargetResult::NoResponse,
+        },
+    }
+}
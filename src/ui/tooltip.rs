@@ -2,7 +2,9 @@ use specs::prelude::*;
 use specs::World;
 
 use crate::{
-    components::{Name, Position},
+    camera,
+    components::{Enemy, Hidden, Name, Position},
+    identification::Identification,
     map::Map,
 };
 
@@ -10,18 +12,37 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut rltk::Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let identification = ecs.fetch::<Identification>();
+    let enemies = ecs.read_storage::<Enemy>();
+    let detecting = camera::player_detects_monsters(ecs);
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 > map.width || mouse_pos.1 > map.height {
+    if mouse_pos.0 >= camera::VIEW_WIDTH || mouse_pos.1 >= camera::VIEW_HEIGHT {
+        return;
+    }
+
+    let mouse_world = camera::screen_to_world(ecs, mouse_pos);
+    if mouse_world.x < 0
+        || mouse_world.x >= map.width
+        || mouse_world.y < 0
+        || mouse_world.y >= map.height
+    {
         return;
     }
 
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, pos) in (&names, &positions).join() {
+    for (name, pos, enemy, _hidden) in (&names, &positions, enemies.maybe(), !&hidden).join() {
+        if pos.x != mouse_world.x || pos.y != mouse_world.y {
+            continue;
+        }
+
         let idx = map.xy_idx(pos.x, pos.y);
 
-        if pos.x == mouse_pos.0 && pos.y == mouse_pos.1 && map.visible_cells[idx] {
-            tooltip.push(name.name.to_string());
+        if map.visible_cells[idx] {
+            tooltip.push(identification.name_of(name));
+        } else if detecting && enemy.is_some() {
+            tooltip.push(format!("{} (sensed)", identification.name_of(name)));
         }
     }
 
@@ -36,9 +57,7 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut rltk::Rltk) {
             if mouse_pos.0 > 40 {
                 let arrow_pos = rltk::Point::new(mouse_pos.0 - 2, mouse_pos.1);
                 let left_x = mouse_pos.0 - width;
-                let mut y = mouse_pos.1;
-
-                for s in tooltip.iter() {
+                for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                     ctx.print_color(
                         left_x,
                         y,
@@ -54,24 +73,21 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut rltk::Rltk) {
                             y,
                             rltk::RGB::named(rltk::WHITE),
                             rltk::RGB::named(rltk::GREY),
-                            &" ".to_string(),
+                            " ".to_string(),
                         );
                     }
-                    y += 1;
                 }
                 ctx.print_color(
                     arrow_pos.x,
                     arrow_pos.y,
                     rltk::RGB::named(rltk::WHITE),
                     rltk::RGB::named(rltk::GREY),
-                    &"->".to_string(),
+                    "->".to_string(),
                 );
             } else {
                 let arrow_pos = rltk::Point::new(mouse_pos.0 + 1, mouse_pos.1);
                 let left_x = mouse_pos.0 + 3;
-                let mut y = mouse_pos.1;
-
-                for s in tooltip.iter() {
+                for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                     ctx.print_color(
                         left_x + 1,
                         y,
@@ -87,10 +103,9 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut rltk::Rltk) {
                             y,
                             rltk::RGB::named(rltk::WHITE),
                             rltk::RGB::named(rltk::GREY),
-                            &" ".to_string(),
+                            " ".to_string(),
                         );
                     }
-                    y += 1;
                 }
 
                 ctx.print_color(
@@ -98,7 +113,7 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut rltk::Rltk) {
                     arrow_pos.y,
                     rltk::RGB::named(rltk::WHITE),
                     rltk::RGB::named(rltk::GREY),
-                    &"<-".to_string(),
+                    "<-".to_string(),
                 );
             }
         }
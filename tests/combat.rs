@@ -0,0 +1,113 @@
+use rltk::RandomNumberGenerator;
+use whatever::{
+    combat::{self, AttackOutcome},
+    components::DamageDice,
+};
+
+const ROLLS: usize = 2000;
+
+const SHORTSWORD: DamageDice = DamageDice {
+    n_dice: 1,
+    die_type: 6,
+};
+
+fn roll_attacks(seed: u64, attack_bonus: i32, armor_class: i32) -> Vec<AttackOutcome> {
+    let mut rng = RandomNumberGenerator::seeded(seed);
+
+    (0..ROLLS)
+        .map(|_| combat::roll_attack(&mut rng, attack_bonus, armor_class))
+        .collect()
+}
+
+fn count(outcomes: &[AttackOutcome], outcome: AttackOutcome) -> usize {
+    outcomes.iter().filter(|rolled| **rolled == outcome).count()
+}
+
+#[test]
+fn natural_rolls_settle_the_attack_whatever_the_odds() {
+    assert_eq!(combat::attack_outcome(1, 100, 10), AttackOutcome::Fumble);
+    assert_eq!(
+        combat::attack_outcome(20, -100, 10),
+        AttackOutcome::Critical
+    );
+}
+
+#[test]
+fn attack_hits_when_it_meets_the_armor_class() {
+    assert_eq!(combat::attack_outcome(8, 4, 12), AttackOutcome::Hit);
+    assert_eq!(combat::attack_outcome(7, 4, 12), AttackOutcome::Miss);
+}
+
+#[test]
+fn defense_raises_the_armor_class() {
+    assert_eq!(combat::armor_class(0), combat::BASE_ARMOR_CLASS);
+    assert_eq!(combat::armor_class(3), combat::BASE_ARMOR_CLASS + 3);
+}
+
+#[test]
+fn same_seed_rolls_same_attacks() {
+    assert_eq!(roll_attacks(7, 5, 12), roll_attacks(7, 5, 12));
+}
+
+#[test]
+fn overwhelming_attacks_still_fumble() {
+    let outcomes = roll_attacks(1, 100, combat::armor_class(0));
+
+    assert_eq!(count(&outcomes, AttackOutcome::Miss), 0);
+    assert!(count(&outcomes, AttackOutcome::Fumble) > 0);
+    assert!(count(&outcomes, AttackOutcome::Hit) > count(&outcomes, AttackOutcome::Fumble));
+}
+
+#[test]
+fn hopeless_attacks_still_crit() {
+    let outcomes = roll_attacks(2, -100, combat::armor_class(0));
+
+    assert_eq!(count(&outcomes, AttackOutcome::Hit), 0);
+    assert!(count(&outcomes, AttackOutcome::Critical) > 0);
+    assert!(count(&outcomes, AttackOutcome::Miss) > count(&outcomes, AttackOutcome::Critical));
+}
+
+#[test]
+fn more_defense_means_fewer_hits() {
+    let landed = |defense| {
+        let outcomes = roll_attacks(3, 5, combat::armor_class(defense));
+        count(&outcomes, AttackOutcome::Hit) + count(&outcomes, AttackOutcome::Critical)
+    };
+
+    assert!(landed(0) > landed(4));
+    assert!(landed(4) > landed(8));
+}
+
+#[test]
+fn damage_rolls_the_weapon_dice_plus_bonus() {
+    let mut rng = RandomNumberGenerator::seeded(4);
+
+    let hits: Vec<i32> = (0..ROLLS)
+        .map(|_| combat::roll_damage(&mut rng, SHORTSWORD, 2, AttackOutcome::Hit))
+        .collect();
+
+    assert!(hits.iter().all(|damage| (3..=8).contains(damage)));
+    assert!(hits.contains(&3));
+    assert!(hits.contains(&8));
+}
+
+#[test]
+fn criticals_double_the_damage_dice() {
+    let mut rng = RandomNumberGenerator::seeded(5);
+
+    let crits: Vec<i32> = (0..ROLLS)
+        .map(|_| combat::roll_damage(&mut rng, SHORTSWORD, 2, AttackOutcome::Critical))
+        .collect();
+
+    assert!(crits.iter().all(|damage| (4..=14).contains(damage)));
+    assert!(crits.contains(&14));
+}
+
+#[test]
+fn landed_attacks_always_do_some_damage() {
+    let mut rng = RandomNumberGenerator::seeded(6);
+
+    assert!(
+        (0..ROLLS).all(|_| combat::roll_damage(&mut rng, SHORTSWORD, -10, AttackOutcome::Hit) == 1)
+    );
+}
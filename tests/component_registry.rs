@@ -0,0 +1,328 @@
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker, SimpleMarkerAllocator};
+use whatever::{
+    components::*,
+    for_each_component,
+    identification::Identification,
+    map::Map,
+    registry::{self, register_components},
+    save::{self, SaveMetadata},
+};
+
+macro_rules! count_components {
+    ($ecs:expr, $( $type:ty),*) => {
+        vec![
+            $(
+            (stringify!($type), $ecs.read_storage::<$type>().join().count()),
+            )*
+        ]
+    };
+}
+
+macro_rules! held_components {
+    ($ecs:expr, $entity:expr, $( $type:ty),*) => {
+        vec![
+            $(
+            $ecs.read_storage::<$type>().get($entity).is_some(),
+            )*
+        ]
+    };
+}
+
+fn component_counts(ecs: &World) -> Vec<(&'static str, usize)> {
+    for_each_component!(count_components!(ecs,))
+}
+
+/// Whether the entity has each registered component, in registration order
+fn components_of(ecs: &World, entity: Entity) -> Vec<bool> {
+    for_each_component!(held_components!(ecs, entity,))
+}
+
+/// Builds a world holding at least one of every registered component
+fn populated_world() -> World {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+    ecs.insert(SimpleMarkerAllocator::<SerializeOnSave>::new());
+
+    let player = ecs
+        .create_entity()
+        .with(Position { x: 1, y: 2 })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: rltk::RGB::named(rltk::YELLOW),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 0,
+        })
+        .with(Player {})
+        .with(Name {
+            name: "Player".to_string(),
+        })
+        .with(CombatStats {
+            max_hp: 30,
+            hp: 30,
+            defense: 2,
+            power: 5,
+        })
+        .with(Attributes {
+            strength: 10,
+            dexterity: 12,
+        })
+        .with(Purse { gold: 25 })
+        .with(LightSource {
+            color: rltk::RGB::from_f32(1., 0.9, 0.6),
+            radius: 5,
+            lit: true,
+        })
+        .with(Viewshed {
+            visible_cells: vec![rltk::Point::new(1, 2)],
+            range: 8,
+            dirty: true,
+        })
+        .with(Damage { amount: vec![3] })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    let enemy = ecs
+        .create_entity()
+        .with(Position { x: 2, y: 2 })
+        .with(Enemy {})
+        .with(OpensDoors {})
+        .with(BlocksCell {})
+        .with(StatusEffect {
+            effect: StatusEffectType::Confusion,
+            print_as: "confusing".to_string(),
+            turns: 4,
+        })
+        .with(DetectsMonsters { turns: 20 })
+        .with(DesiresMelee { target: player })
+        .with(EntityMoved {})
+        .with(Alerted {})
+        .with(TeleportSpell { range: 6 })
+        .with(DesiresTeleport {
+            destination: Some(rltk::Point::new(1, 1)),
+        })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    ecs.create_entity()
+        .with(Position { x: 2, y: 3 })
+        .with(Shopkeeper {})
+        .with(GoldPile { amount: 10 })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    ecs.create_entity()
+        .with(Position { x: 3, y: 2 })
+        .with(Trap {
+            kind: TrapType::Spike,
+        })
+        .with(Hidden {})
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    let item = ecs
+        .create_entity()
+        .with(Item {})
+        .with(Consumable {})
+        .with(Stackable { quantity: 3 })
+        .with(Shatters {})
+        .with(ProvidesHealing { heal_amount: 8 })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 20 })
+        .with(AreaOfEffect { radius: 3 })
+        .with(MagicMapper {})
+        .with(Teleports {})
+        .with(Charges {
+            current: 2,
+            max: 5,
+            destroyed_when_empty: false,
+        })
+        .with(Recharges {})
+        .with(Identifies {})
+        .with(RemovesCurse {})
+        .with(Equippable {
+            slot: EquipmentSlot::Melee,
+        })
+        .with(Equipped {
+            owner: player,
+            slot: EquipmentSlot::Melee,
+        })
+        .with(DamageDice {
+            n_dice: 1,
+            die_type: 6,
+        })
+        .with(MeleePowerBonus { power: 2 })
+        .with(DefenseBonus { defense: -1 })
+        .with(RangedWeapon {
+            range: 8,
+            damage: DamageDice {
+                n_dice: 1,
+                die_type: 8,
+            },
+            ammo: AmmoType::Arrow,
+        })
+        .with(Ammunition {
+            ammo: AmmoType::Arrow,
+        })
+        .with(Cursed { known: false })
+        .with(InInventory { owner: player })
+        .with(Weight { weight: 1 })
+        .with(Value { gold: 20 })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    ecs.create_entity()
+        .with(DesiresAcquireItem {
+            acquired_by: enemy,
+            item,
+        })
+        .with(DesiresUseItem {
+            item,
+            target: Some(rltk::Point::new(2, 2)),
+        })
+        .with(DesiresDropItem { item })
+        .with(DesiresEquipItem { item })
+        .with(DesiresThrowItem {
+            item,
+            target: rltk::Point::new(3, 3),
+        })
+        .with(DesiresFire {
+            ammo: item,
+            target: enemy,
+        })
+        .with(SerializationHelper {
+            map: Map::default(),
+            metadata: SaveMetadata::default(),
+            identification: Identification::default(),
+        })
+        .marked::<SimpleMarker<SerializeOnSave>>()
+        .build();
+
+    ecs
+}
+
+#[test]
+fn every_registered_component_round_trips() {
+    let mut ecs = populated_world();
+    let before = component_counts(&ecs);
+
+    for (name, count) in before.iter() {
+        assert!(*count > 0, "no {} in the test world", name);
+    }
+
+    let body = save::serialize_components(&ecs);
+
+    ecs.delete_all();
+    ecs.maintain();
+    assert!(component_counts(&ecs).iter().all(|(_, count)| *count == 0));
+
+    save::deserialize_components(&mut ecs, std::str::from_utf8(&body).unwrap())
+        .expect("unable to deserialize components");
+    ecs.maintain();
+
+    assert_eq!(before, component_counts(&ecs));
+
+    let stats = ecs.read_storage::<CombatStats>();
+    let stats = stats.join().next().unwrap();
+    assert_eq!(
+        (stats.max_hp, stats.hp, stats.defense, stats.power),
+        (30, 30, 2, 5)
+    );
+
+    let mut positions: Vec<(i32, i32)> = ecs
+        .read_storage::<Position>()
+        .join()
+        .map(|pos| (pos.x, pos.y))
+        .collect();
+    positions.sort();
+    assert_eq!(positions, vec![(1, 2), (2, 2), (2, 3), (3, 2)]);
+
+    let trap = ecs.read_storage::<Trap>();
+    assert_eq!(trap.join().next().unwrap().kind, TrapType::Spike);
+
+    let charges = ecs.read_storage::<Charges>();
+    let charges = charges.join().next().unwrap();
+    assert_eq!(
+        (charges.current, charges.max, charges.destroyed_when_empty),
+        (2, 5, false)
+    );
+
+    let stack = ecs.read_storage::<Stackable>();
+    assert_eq!(stack.join().next().unwrap().quantity, 3);
+
+    let equipped = ecs.read_storage::<Equipped>();
+    assert_eq!(equipped.join().next().unwrap().slot, EquipmentSlot::Melee);
+
+    let weapon = ecs.read_storage::<RangedWeapon>();
+    assert_eq!(
+        weapon.join().next().unwrap().damage,
+        DamageDice {
+            n_dice: 1,
+            die_type: 8,
+        }
+    );
+}
+
+#[test]
+fn entity_references_survive_round_trip() {
+    let mut ecs = populated_world();
+    let body = save::serialize_components(&ecs);
+
+    ecs.delete_all();
+    ecs.maintain();
+    save::deserialize_components(&mut ecs, std::str::from_utf8(&body).unwrap())
+        .expect("unable to deserialize components");
+
+    let players = ecs.read_storage::<Player>();
+    let melee = ecs.read_storage::<DesiresMelee>();
+    let inventory = ecs.read_storage::<InInventory>();
+
+    let (player, _) = (&ecs.entities(), &players).join().next().unwrap();
+
+    assert!(melee.join().all(|intent| intent.target == player));
+    assert!(inventory.join().all(|item| item.owner == player));
+    assert!(ecs
+        .read_storage::<Equipped>()
+        .join()
+        .all(|item| item.owner == player));
+}
+
+#[test]
+fn cloned_entity_has_every_component_of_the_original() {
+    let mut ecs = populated_world();
+    let item = {
+        let items = ecs.read_storage::<Item>();
+        (&ecs.entities(), &items).join().next().unwrap().0
+    };
+
+    let copy = registry::clone_entity(&mut ecs, item);
+
+    assert_ne!(item, copy);
+    assert_eq!(components_of(&ecs, item), components_of(&ecs, copy));
+    assert_eq!(
+        ecs.read_storage::<Stackable>().get(copy).unwrap().quantity,
+        3
+    );
+}
+
+#[test]
+fn truncated_save_body_is_an_error() {
+    let mut ecs = populated_world();
+    let body = save::serialize_components(&ecs);
+    let truncated = std::str::from_utf8(&body[..body.len() / 2]).unwrap();
+
+    ecs.delete_all();
+    ecs.maintain();
+
+    assert!(save::deserialize_components(&mut ecs, truncated).is_err());
+}
+
+#[test]
+fn helper_without_run_bookkeeping_is_rejected() {
+    let helper: Result<SerializationHelper, _> = serde_json::from_str(&format!(
+        "{{\"map\":{}}}",
+        serde_json::to_string(&Map::default()).unwrap()
+    ));
+
+    assert!(helper.is_err());
+}
@@ -0,0 +1,305 @@
+use rltk::RandomNumberGenerator;
+use whatever::{
+    components::Position,
+    geometry::Rect,
+    map::{CellType, Map},
+    map_builders::{self, *},
+};
+
+// How many seeds the property tests run through
+const SEEDS: u64 = 2000;
+const SEEDS_PER_GENERATOR: u64 = 200;
+
+/// One of every generator, including the prefab and sample-based ones that wrap another
+fn every_generator(
+    depth: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Vec<(&'static str, Box<dyn MapBuilder>)> {
+    let section = rng.random_slice_entry(map_builders::SECTIONS).unwrap();
+    let level = rng.random_slice_entry(map_builders::LEVELS).unwrap();
+
+    vec![
+        (
+            "rooms and tunnels",
+            Box::new(RoomsAndTunnelsBuilder::new(depth)),
+        ),
+        ("caves", Box::new(CellularAutomataBuilder::new(depth))),
+        (
+            "bsp straight",
+            Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Straight)),
+        ),
+        (
+            "bsp dogleg",
+            Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Dogleg)),
+        ),
+        (
+            "bsp nearest",
+            Box::new(BspDungeonBuilder::new(
+                depth,
+                CorridorStyle::NearestNeighbour,
+            )),
+        ),
+        ("prefab level", Box::new(PrefabBuilder::level(depth, level))),
+        (
+            "vaults",
+            Box::new(PrefabBuilder::vaults(
+                depth,
+                Box::new(RoomsAndTunnelsBuilder::new(depth)),
+            )),
+        ),
+        (
+            "shop",
+            Box::new(PrefabBuilder::shop(
+                depth,
+                Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Straight)),
+            )),
+        ),
+        (
+            "section",
+            Box::new(PrefabBuilder::sectional(
+                depth,
+                section,
+                Box::new(CellularAutomataBuilder::new(depth)),
+            )),
+        ),
+        (
+            "terrain",
+            Box::new(TerrainBuilder::new(
+                depth,
+                Box::new(BspDungeonBuilder::new(depth, CorridorStyle::Dogleg)),
+            )),
+        ),
+        (
+            "wfc",
+            Box::new(WaveformCollapseBuilder::new(
+                depth,
+                Box::new(CellularAutomataBuilder::new(depth)),
+            )),
+        ),
+    ]
+}
+
+#[test]
+fn every_seed_builds_a_valid_level() {
+    for seed in 0..SEEDS {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let depth = 1 + (seed % 10) as i32;
+
+        let builder = build_level(depth, &mut rng);
+
+        assert_eq!(
+            validate_level(&builder.get_map(), &builder.get_starting_position()),
+            Ok(()),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn generators_rarely_need_regenerating() {
+    let mut failures: Vec<(&'static str, u64)> = Vec::new();
+
+    for seed in 0..SEEDS_PER_GENERATOR {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let depth = 1 + (seed % 10) as i32;
+
+        for (name, mut builder) in every_generator(depth, &mut rng) {
+            builder.build_map(&mut rng);
+
+            if validate_level(&builder.get_map(), &builder.get_starting_position()).is_err() {
+                match failures.iter_mut().find(|(failed, _)| *failed == name) {
+                    Some((_, count)) => *count += 1,
+                    None => failures.push((name, 1)),
+                }
+            }
+        }
+    }
+
+    // No more than one level in twenty thrown away
+    for (name, count) in failures {
+        assert!(
+            count * 20 <= SEEDS_PER_GENERATOR,
+            "{} failed {} of {} seeds",
+            name,
+            count,
+            SEEDS_PER_GENERATOR
+        );
+    }
+}
+
+#[test]
+fn room_generators_put_the_stairs_as_far_as_can_be_walked() {
+    for seed in 0..SEEDS_PER_GENERATOR {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let builders: Vec<Box<dyn MapBuilder>> = vec![
+            Box::new(RoomsAndTunnelsBuilder::new(1)),
+            Box::new(BspDungeonBuilder::new(1, CorridorStyle::Straight)),
+        ];
+
+        for mut builder in builders {
+            builder.build_map(&mut rng);
+
+            let mut map = builder.get_map();
+            let start = builder.get_starting_position();
+            map.populate_blocked();
+            map.set_closed_doors_blocked(false);
+
+            let dijkstra = rltk::DijkstraMap::new(
+                map.width,
+                map.height,
+                &[map.xy_idx(start.x, start.y)],
+                &map,
+                2000.0,
+            );
+            let stairs_idx = map
+                .cells
+                .iter()
+                .position(|cell| *cell == CellType::DownStairs)
+                .unwrap();
+            let farthest_floor = map
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(idx, cell)| **cell == CellType::Floor && dijkstra.map[*idx] < f32::MAX)
+                .map(|(idx, _)| dijkstra.map[idx])
+                .fold(0.0, f32::max);
+
+            assert!(dijkstra.map[stairs_idx] >= farthest_floor, "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn fallback_level_always_passes_validation() {
+    for seed in 0..SEEDS_PER_GENERATOR {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let depth = 1 + (seed % 10) as i32;
+
+        let mut builder = fallback_builder(depth);
+        builder.build_map(&mut rng);
+
+        assert_eq!(
+            validate_level(&builder.get_map(), &builder.get_starting_position()),
+            Ok(()),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn same_seed_builds_same_level() {
+    for seed in 0..50 {
+        let first = build_level(1, &mut RandomNumberGenerator::seeded(seed));
+        let second = build_level(1, &mut RandomNumberGenerator::seeded(seed));
+
+        assert!(
+            first.get_map().cells == second.get_map().cells,
+            "seed {}",
+            seed
+        );
+        assert_eq!(
+            first.get_starting_position().x,
+            second.get_starting_position().x
+        );
+        assert_eq!(
+            first.get_starting_position().y,
+            second.get_starting_position().y
+        );
+    }
+}
+
+/// A 20x20 map holding a 10x10 room with the stairs in its far corner
+fn small_level() -> (Map, Position) {
+    let mut map = Map::new(1, 20, 20);
+
+    for y in 2..12 {
+        for x in 2..12 {
+            let idx = map.xy_idx(x, y);
+            map.cells[idx] = CellType::Floor;
+        }
+    }
+
+    let stairs_idx = map.xy_idx(11, 11);
+    map.cells[stairs_idx] = CellType::DownStairs;
+
+    (map, Position { x: 2, y: 2 })
+}
+
+#[test]
+fn validation_accepts_a_connected_level() {
+    let (map, start) = small_level();
+
+    assert_eq!(validate_level(&map, &start), Ok(()));
+}
+
+#[test]
+fn validation_rejects_broken_levels() {
+    let (map, _) = small_level();
+    assert_eq!(
+        validate_level(&map, &Position { x: 0, y: 0 }),
+        Err(LevelProblem::StartNotFloor)
+    );
+
+    let (mut map, start) = small_level();
+    let stairs_idx = map.xy_idx(11, 11);
+    map.cells[stairs_idx] = CellType::Floor;
+    assert_eq!(validate_level(&map, &start), Err(LevelProblem::NoStairs));
+
+    let (mut map, start) = small_level();
+    let idx = map.xy_idx(15, 15);
+    map.cells[idx] = CellType::Floor;
+    assert_eq!(
+        validate_level(&map, &start),
+        Err(LevelProblem::UnreachableCell { x: 15, y: 15 })
+    );
+
+    let (mut map, start) = small_level();
+    let old_stairs = map.xy_idx(11, 11);
+    let new_stairs = map.xy_idx(15, 15);
+    map.cells[old_stairs] = CellType::Floor;
+    map.cells[new_stairs] = CellType::DownStairs;
+    assert_eq!(
+        validate_level(&map, &start),
+        Err(LevelProblem::UnreachableStairs)
+    );
+
+    let (mut map, start) = small_level();
+    for y in 2..12 {
+        for x in 6..12 {
+            let idx = map.xy_idx(x, y);
+            map.cells[idx] = CellType::Wall;
+        }
+    }
+    let stairs_idx = map.xy_idx(5, 5);
+    map.cells[stairs_idx] = CellType::DownStairs;
+    assert_eq!(
+        validate_level(&map, &start),
+        Err(LevelProblem::TooLittleFloor { percent: 9 })
+    );
+
+    let (mut map, start) = small_level();
+    map.rooms.push(Rect::new(1, 1, 10, 10));
+    assert_eq!(
+        validate_level(&map, &start),
+        Err(LevelProblem::TooFewRooms(1))
+    );
+}
+
+#[test]
+fn validation_leaves_doors_passable() {
+    let (mut map, start) = small_level();
+
+    // Wall the stairs' corner off behind a closed door
+    for i in 8..12 {
+        let idx = map.xy_idx(8, i);
+        map.cells[idx] = CellType::Wall;
+        let idx = map.xy_idx(i, 8);
+        map.cells[idx] = CellType::Wall;
+    }
+    let door_idx = map.xy_idx(8, 10);
+    map.cells[door_idx] = CellType::ClosedDoor;
+
+    assert_eq!(validate_level(&map, &start), Ok(()));
+}
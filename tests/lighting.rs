@@ -0,0 +1,94 @@
+use specs::prelude::*;
+use whatever::{
+    components::*,
+    map::{CellType, Map},
+    registry::register_components,
+    systems::LightingSystem,
+};
+
+/// A dark, open 20x20 level with someone standing in the middle whose view is up to date
+fn dark_level() -> (World, Entity) {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+
+    let mut map = Map::new(1, 20, 20);
+    for cell in map.cells.iter_mut() {
+        *cell = CellType::Floor;
+    }
+    ecs.insert(map);
+
+    let watcher = ecs
+        .create_entity()
+        .with(Position { x: 10, y: 10 })
+        .with(Viewshed {
+            visible_cells: Vec::new(),
+            range: 8,
+            dirty: false,
+        })
+        .build();
+
+    (ecs, watcher)
+}
+
+fn torch(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(LightSource {
+            color: rltk::RGB::from_f32(1., 0.6, 0.2),
+            radius: 3,
+            lit: true,
+        })
+        .build()
+}
+
+fn is_dirty(ecs: &World, entity: Entity) -> bool {
+    ecs.read_storage::<Viewshed>().get(entity).unwrap().dirty
+}
+
+fn settle(ecs: &World, watcher: Entity) {
+    LightingSystem {}.run_now(ecs);
+    ecs.write_storage::<Viewshed>()
+        .get_mut(watcher)
+        .unwrap()
+        .dirty = false;
+}
+
+#[test]
+fn unchanged_light_leaves_viewsheds_alone() {
+    let (mut ecs, watcher) = dark_level();
+    torch(&mut ecs, 12, 10);
+    settle(&ecs, watcher);
+
+    LightingSystem {}.run_now(&ecs);
+
+    assert!(!is_dirty(&ecs, watcher));
+}
+
+#[test]
+fn moving_a_light_in_view_dirties_the_viewshed() {
+    let (mut ecs, watcher) = dark_level();
+    let light = torch(&mut ecs, 12, 10);
+    settle(&ecs, watcher);
+
+    ecs.write_storage::<Position>()
+        .insert(light, Position { x: 8, y: 10 })
+        .expect("unable to move torch");
+    LightingSystem {}.run_now(&ecs);
+
+    assert!(is_dirty(&ecs, watcher));
+}
+
+#[test]
+fn light_changing_out_of_range_goes_unnoticed() {
+    let (mut ecs, watcher) = dark_level();
+    let light = torch(&mut ecs, 1, 1);
+    settle(&ecs, watcher);
+
+    ecs.write_storage::<LightSource>()
+        .get_mut(light)
+        .unwrap()
+        .lit = false;
+    LightingSystem {}.run_now(&ecs);
+
+    assert!(!is_dirty(&ecs, watcher));
+}
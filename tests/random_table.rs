@@ -0,0 +1,19 @@
+use rltk::RandomNumberGenerator;
+use whatever::random_table::RandomTable;
+
+#[test]
+fn entries_without_weight_are_never_rolled_and_dont_hide_later_ones() {
+    let table = RandomTable::new()
+        .add("Potion", 2)
+        .add("Crossbow", -1)
+        .add("Bolt", 0)
+        .add("Arrow", 3);
+    let mut rng = RandomNumberGenerator::seeded(1);
+
+    let rolls: Vec<String> = (0..500).map(|_| table.roll(&mut rng)).collect();
+
+    assert!(rolls.iter().any(|rolled| rolled == "Arrow"));
+    assert!(!rolls
+        .iter()
+        .any(|rolled| rolled == "Crossbow" || rolled == "Bolt"));
+}
@@ -0,0 +1,80 @@
+use specs::prelude::*;
+use specs::saveload::SimpleMarkerAllocator;
+use whatever::{components::*, registry::register_components, systems::inventory};
+
+fn world_with_owner() -> (World, Entity) {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+    ecs.insert(SimpleMarkerAllocator::<SerializeOnSave>::new());
+
+    let owner = ecs.create_entity().build();
+    (ecs, owner)
+}
+
+fn wand(ecs: &mut World, current: i32) -> Entity {
+    ecs.create_entity()
+        .with(Item {})
+        .with(Name {
+            name: "Wand".to_string(),
+        })
+        .with(Stackable { quantity: 1 })
+        .with(Charges {
+            current,
+            max: 5,
+            destroyed_when_empty: false,
+        })
+        .build()
+}
+
+fn carried(ecs: &World, owner: Entity) -> Vec<i32> {
+    let inventory = ecs.read_storage::<InInventory>();
+    let stacks = ecs.read_storage::<Stackable>();
+
+    (&inventory, &stacks)
+        .join()
+        .filter(|(item, _)| item.owner == owner)
+        .map(|(_, stack)| stack.quantity)
+        .collect()
+}
+
+#[test]
+fn alike_items_share_a_stack() {
+    let (mut ecs, owner) = world_with_owner();
+
+    let first = wand(&mut ecs, 3);
+    inventory::add_to_inventory(&mut ecs, first, owner);
+    let second = wand(&mut ecs, 3);
+    inventory::add_to_inventory(&mut ecs, second, owner);
+    ecs.maintain();
+
+    assert_eq!(carried(&ecs, owner), vec![2]);
+}
+
+#[test]
+fn items_with_different_charges_stack_apart() {
+    let (mut ecs, owner) = world_with_owner();
+
+    let first = wand(&mut ecs, 3);
+    inventory::add_to_inventory(&mut ecs, first, owner);
+    let second = wand(&mut ecs, 1);
+    inventory::add_to_inventory(&mut ecs, second, owner);
+    ecs.maintain();
+
+    assert_eq!(carried(&ecs, owner), vec![1, 1]);
+}
+
+#[test]
+fn cursed_items_stack_apart_from_clean_ones() {
+    let (mut ecs, owner) = world_with_owner();
+
+    let first = wand(&mut ecs, 3);
+    inventory::add_to_inventory(&mut ecs, first, owner);
+    let second = wand(&mut ecs, 3);
+    ecs.write_storage::<Cursed>()
+        .insert(second, Cursed { known: false })
+        .expect("unable to curse wand");
+    inventory::add_to_inventory(&mut ecs, second, owner);
+    ecs.maintain();
+
+    assert_eq!(carried(&ecs, owner), vec![1, 1]);
+}